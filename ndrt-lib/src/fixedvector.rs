@@ -1,3 +1,4 @@
+use crate::matrix::Matrix;
 use crate::vector::Vector;
 use crate::Float;
 use std::fmt::Display;
@@ -8,6 +9,8 @@ pub struct FixedVector<const L: usize> {
 }
 
 impl<const L: usize> Vector for FixedVector<L> {
+    type Matrix = [FixedVector<L>; L];
+
     fn new() -> Self {
         Self {
             components: [0.0; L],
@@ -39,6 +42,17 @@ impl<const L: usize> Vector for FixedVector<L> {
     fn components(&self) -> &[Float] {
         &self.components
     }
+
+    #[inline]
+    fn components_mut(&mut self) -> &mut [Float] {
+        &mut self.components
+    }
+}
+
+impl<const L: usize> Matrix<FixedVector<L>> for [FixedVector<L>; L] {
+    fn zero() -> Self {
+        [FixedVector::new(); L]
+    }
 }
 
 impl<const L: usize> Display for FixedVector<L> {
@@ -1,7 +1,11 @@
 pub type Float = f32;
 
 mod fixedvector;
+mod matrix;
+mod polytope;
 mod vector;
 
 pub use fixedvector::FixedVector;
+pub use matrix::Matrix;
+pub use polytope::RegularPolytope;
 pub use vector::Vector;
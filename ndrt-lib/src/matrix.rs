@@ -0,0 +1,110 @@
+use crate::vector::Vector;
+use crate::Float;
+use std::fmt::Debug;
+
+// Square matrix stored as rows of vectors. Every vector type has exactly one
+// matrix type matching its dimension, see `Vector::Matrix`.
+pub trait Matrix<V: Vector>: Sized + Copy + Debug + AsRef<[V]> + AsMut<[V]> {
+    fn zero() -> Self;
+
+    fn identity() -> Self {
+        let mut result = Self::zero();
+
+        for (index, row) in result.as_mut().iter_mut().enumerate() {
+            row.components_mut()[index] = 1.0;
+        }
+
+        result
+    }
+
+    fn from_rows(rows: impl Iterator<Item = V>) -> Self {
+        let mut result = Self::zero();
+
+        for (row, value) in result.as_mut().iter_mut().zip(rows) {
+            *row = value;
+        }
+
+        result
+    }
+
+    fn from_columns(columns: impl Iterator<Item = V>) -> Self {
+        Self::from_rows(columns).transpose()
+    }
+
+    fn transpose(&self) -> Self {
+        let mut result = Self::zero();
+
+        for (i, row) in self.as_ref().iter().enumerate() {
+            for (j, value) in row.components().iter().enumerate() {
+                result.as_mut()[j].components_mut()[i] = *value;
+            }
+        }
+
+        result
+    }
+
+    // Matrix vector product
+    fn transform(&self, v: &V) -> V {
+        V::from_iter(self.as_ref().iter().map(|row| row.dot(v)))
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        let columns = other.transpose();
+
+        Self::from_rows(
+            self.as_ref()
+                .iter()
+                .map(|row| V::from_iter(columns.as_ref().iter().map(|col| row.dot(col)))),
+        )
+    }
+
+    // Gauss-Jordan elimination with partial pivoting, `None` if the matrix is singular
+    fn inverse(&self) -> Option<Self> {
+        let mut left = *self;
+        let mut right = Self::identity();
+        let size = left.as_ref().len();
+
+        // Pivots this much smaller than the entries are nothing but rounding errors
+        let largest = self
+            .as_ref()
+            .iter()
+            .flat_map(|row| row.components().iter())
+            .fold(0.0, |largest: Float, c| largest.max(c.abs()));
+        let epsilon = largest * size as Float * Float::EPSILON;
+
+        for col in 0..size {
+            let pivot = (col..size)
+                .max_by(|a, b| {
+                    let a = left.as_ref()[*a].components()[col].abs();
+                    let b = left.as_ref()[*b].components()[col].abs();
+                    a.partial_cmp(&b).unwrap()
+                })
+                .unwrap();
+
+            if left.as_ref()[pivot].components()[col].abs() <= epsilon {
+                return None;
+            }
+
+            left.as_mut().swap(col, pivot);
+            right.as_mut().swap(col, pivot);
+
+            let scale = 1.0 / left.as_ref()[col].components()[col];
+            left.as_mut()[col] = left.as_ref()[col] * scale;
+            right.as_mut()[col] = right.as_ref()[col] * scale;
+
+            for row in 0..size {
+                if row == col {
+                    continue;
+                }
+
+                let factor = left.as_ref()[row].components()[col];
+                if factor != 0.0 {
+                    left.as_mut()[row] = left.as_ref()[row] - left.as_ref()[col] * factor;
+                    right.as_mut()[row] = right.as_ref()[row] - right.as_ref()[col] * factor;
+                }
+            }
+        }
+
+        Some(right)
+    }
+}
@@ -0,0 +1,374 @@
+use crate::Float;
+
+const EPSILON: Float = 1e-4;
+
+// A regular polytope centered at the origin with a circumradius of 1
+#[derive(Debug, Clone)]
+pub struct RegularPolytope {
+    pub dimension: usize,
+    pub vertices: Vec<Vec<Float>>,
+    // Pairs of indices into `vertices`
+    pub edges: Vec<(usize, usize)>,
+    // Unit normal and offset of every facet, inside is where `normal . p <= offset`
+    pub facets: Vec<(Vec<Float>, Float)>,
+}
+
+impl RegularPolytope {
+    // Generalization of the tetrahedron, N + 1 vertices
+    pub fn simplex(dimension: usize) -> Self {
+        // The unit vectors plus one point on the diagonal form a regular simplex
+        let diagonal = (1.0 - Float::sqrt(dimension as Float + 1.0)) / dimension as Float;
+
+        let mut vertices: Vec<Vec<Float>> = (0..dimension)
+            .map(|axis| {
+                let mut vertex = vec![0.0; dimension];
+                vertex[axis] = 1.0;
+                vertex
+            })
+            .collect();
+        vertices.push(vec![diagonal; dimension]);
+
+        let center: Vec<Float> = (0..dimension)
+            .map(|axis| vertices.iter().map(|v| v[axis]).sum::<Float>() / (dimension + 1) as Float)
+            .collect();
+        let vertices: Vec<Vec<Float>> = vertices
+            .iter()
+            .map(|v| {
+                normalize(
+                    &v.iter()
+                        .zip(&center)
+                        .map(|(a, b)| a - b)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+
+        // Each facet lies opposite of a vertex
+        let normals = vertices
+            .iter()
+            .map(|v| v.iter().map(|c| -c).collect())
+            .collect();
+
+        Self::from_vertices(dimension, vertices).with_facets(normals)
+    }
+
+    // Generalization of the cube, 2^N vertices
+    pub fn hypercube(dimension: usize) -> Self {
+        let vertices = sign_combinations(&vec![1.0; dimension]);
+        let normals = unit_vectors(dimension);
+
+        Self::from_vertices(dimension, vertices).with_facets(normals)
+    }
+
+    // Generalization of the octahedron, 2N vertices
+    pub fn cross_polytope(dimension: usize) -> Self {
+        let vertices = unit_vectors(dimension);
+        let normals = sign_combinations(&vec![1.0; dimension]);
+
+        Self::from_vertices(dimension, vertices).with_facets(normals)
+    }
+
+    // The self-dual 4D polytope made up of 24 octahedra
+    pub fn cell_24() -> Self {
+        let vertices = permutations(&[1.0, 1.0, 0.0, 0.0], false);
+        let normals = dual_24_cell();
+
+        Self::from_vertices(4, vertices).with_facets(normals)
+    }
+
+    // The 4D polytope made up of 120 dodecahedra, its vertices are the cell
+    // centers of the 600-cell and vice versa
+    pub fn cell_120() -> Self {
+        let cell_600 = Self::from_vertices(4, vertices_600_cell());
+        let vertices = cell_600.tetrahedra_centers();
+
+        Self::from_vertices(4, vertices).with_facets(cell_600.vertices)
+    }
+
+    // The 4D polytope made up of 600 tetrahedra, dual to the 120-cell
+    pub fn cell_600() -> Self {
+        let cell_600 = Self::from_vertices(4, vertices_600_cell());
+        let normals = cell_600.tetrahedra_centers();
+
+        cell_600.with_facets(normals)
+    }
+
+    // Edges are found as the pairs of vertices with the shortest distance
+    fn from_vertices(dimension: usize, vertices: Vec<Vec<Float>>) -> Self {
+        let vertices: Vec<Vec<Float>> = vertices.iter().map(|v| normalize(v)).collect();
+
+        let mut edge_length = Float::INFINITY;
+        for (i, a) in vertices.iter().enumerate() {
+            for b in &vertices[i + 1..] {
+                edge_length = Float::min(edge_length, distance(a, b));
+            }
+        }
+
+        let mut edges = vec![];
+        for (i, a) in vertices.iter().enumerate() {
+            for (j, b) in vertices.iter().enumerate().skip(i + 1) {
+                if (distance(a, b) - edge_length).abs() < EPSILON {
+                    edges.push((i, j));
+                }
+            }
+        }
+
+        Self {
+            dimension,
+            vertices,
+            edges,
+            facets: vec![],
+        }
+    }
+
+    // The facets are found by pushing the (dual) normals up to the vertices
+    fn with_facets(mut self, normals: Vec<Vec<Float>>) -> Self {
+        self.facets = normals
+            .iter()
+            .map(|normal| {
+                let normal = normalize(normal);
+                let offset = self
+                    .vertices
+                    .iter()
+                    .map(|v| dot(v, &normal))
+                    .fold(-Float::INFINITY, Float::max);
+                (normal, offset)
+            })
+            .collect();
+
+        self
+    }
+
+    // Centers of all tetrahedra formed by the edges, i.e. all sets of 4
+    // vertices that are all connected to each other
+    fn tetrahedra_centers(&self) -> Vec<Vec<Float>> {
+        let count = self.vertices.len();
+        let mut connected = vec![vec![false; count]; count];
+        for (a, b) in &self.edges {
+            connected[*a][*b] = true;
+            connected[*b][*a] = true;
+        }
+
+        let mut centers = vec![];
+        for (a, b) in &self.edges {
+            for c in (b + 1)..count {
+                if !connected[*a][c] || !connected[*b][c] {
+                    continue;
+                }
+
+                for d in (c + 1)..count {
+                    if connected[*a][d] && connected[*b][d] && connected[c][d] {
+                        let center = (0..self.dimension)
+                            .map(|axis| {
+                                self.vertices[*a][axis]
+                                    + self.vertices[*b][axis]
+                                    + self.vertices[c][axis]
+                                    + self.vertices[d][axis]
+                            })
+                            .collect();
+                        centers.push(center);
+                    }
+                }
+            }
+        }
+
+        centers
+    }
+}
+
+fn dot(a: &[Float], b: &[Float]) -> Float {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn distance(a: &[Float], b: &[Float]) -> Float {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum::<Float>()
+        .sqrt()
+}
+
+fn normalize(v: &[Float]) -> Vec<Float> {
+    let length = Float::sqrt(dot(v, v));
+    v.iter().map(|c| c / length).collect()
+}
+
+fn unit_vectors(dimension: usize) -> Vec<Vec<Float>> {
+    (0..dimension)
+        .flat_map(|axis| {
+            [-1.0, 1.0].iter().map(move |sign| {
+                let mut vertex = vec![0.0; dimension];
+                vertex[axis] = *sign;
+                vertex
+            })
+        })
+        .collect()
+}
+
+// All combinations of signs for the non zero components
+fn sign_combinations(base: &[Float]) -> Vec<Vec<Float>> {
+    let mut result = vec![base.to_vec()];
+
+    for (axis, value) in base.iter().enumerate() {
+        if *value != 0.0 {
+            let flipped: Vec<Vec<Float>> = result
+                .iter()
+                .map(|v| {
+                    let mut v = v.clone();
+                    v[axis] = -v[axis];
+                    v
+                })
+                .collect();
+            result.extend(flipped);
+        }
+    }
+
+    result
+}
+
+// All (or only the even) permutations of the components with all combinations of signs
+fn permutations(base: &[Float], even_only: bool) -> Vec<Vec<Float>> {
+    let mut result: Vec<Vec<Float>> = vec![];
+
+    for order in index_permutations(base.len()) {
+        if even_only && !is_even(&order) {
+            continue;
+        }
+
+        let permuted: Vec<Float> = order.iter().map(|i| base[*i]).collect();
+        for v in sign_combinations(&permuted) {
+            // Equal components produce the same vector multiple times
+            if !result.iter().any(|other| distance(other, &v) < EPSILON) {
+                result.push(v);
+            }
+        }
+    }
+
+    result
+}
+
+fn index_permutations(length: usize) -> Vec<Vec<usize>> {
+    if length == 0 {
+        return vec![vec![]];
+    }
+
+    let mut result = vec![];
+    for rest in index_permutations(length - 1) {
+        for position in 0..length {
+            let mut order = rest.clone();
+            order.insert(position, length - 1);
+            result.push(order);
+        }
+    }
+
+    result
+}
+
+fn is_even(order: &[usize]) -> bool {
+    let mut inversions = 0;
+    for (i, a) in order.iter().enumerate() {
+        inversions += order[i + 1..].iter().filter(|b| a > b).count();
+    }
+
+    inversions % 2 == 0
+}
+
+fn dual_24_cell() -> Vec<Vec<Float>> {
+    let mut vertices = unit_vectors(4);
+    vertices.extend(sign_combinations(&[0.5, 0.5, 0.5, 0.5]));
+    vertices
+}
+
+fn vertices_600_cell() -> Vec<Vec<Float>> {
+    let phi = (1.0 + Float::sqrt(5.0)) / 2.0;
+
+    let mut vertices = dual_24_cell();
+    vertices.extend(permutations(&[phi / 2.0, 0.5, 0.5 / phi, 0.0], true));
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(polytope: &RegularPolytope, vertices: usize, edges: usize, facets: usize) {
+        assert_eq!(polytope.vertices.len(), vertices);
+        assert_eq!(polytope.edges.len(), edges);
+        assert_eq!(polytope.facets.len(), facets);
+
+        for vertex in &polytope.vertices {
+            assert_eq!(vertex.len(), polytope.dimension);
+            assert!((dot(vertex, vertex) - 1.0).abs() < EPSILON);
+        }
+
+        // Regular polytopes have the same number of edges at every vertex
+        let degree = 2 * edges / vertices;
+        for index in 0..vertices {
+            let count = polytope
+                .edges
+                .iter()
+                .filter(|(a, b)| *a == index || *b == index)
+                .count();
+            assert_eq!(count, degree);
+        }
+
+        // Every vertex is inside every half-space and every facet touches at
+        // least N vertices
+        let offset = polytope.facets[0].1;
+        for (normal, facet_offset) in &polytope.facets {
+            assert!((facet_offset - offset).abs() < EPSILON);
+
+            let touching = polytope
+                .vertices
+                .iter()
+                .filter(|v| (dot(v, normal) - facet_offset).abs() < EPSILON)
+                .count();
+            assert!(touching >= polytope.dimension);
+
+            for vertex in &polytope.vertices {
+                assert!(dot(vertex, normal) <= facet_offset + EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn simplices() {
+        for n in 2..=9 {
+            check(&RegularPolytope::simplex(n), n + 1, n * (n + 1) / 2, n + 1);
+        }
+    }
+
+    #[test]
+    fn hypercubes() {
+        for n in 2..=9 {
+            check(&RegularPolytope::hypercube(n), 1 << n, n << (n - 1), 2 * n);
+        }
+    }
+
+    #[test]
+    fn cross_polytopes() {
+        for n in 2..=9 {
+            check(
+                &RegularPolytope::cross_polytope(n),
+                2 * n,
+                2 * n * (n - 1),
+                1 << n,
+            );
+        }
+    }
+
+    #[test]
+    fn cell_24() {
+        check(&RegularPolytope::cell_24(), 24, 96, 24);
+    }
+
+    #[test]
+    fn cell_120() {
+        check(&RegularPolytope::cell_120(), 600, 1200, 120);
+    }
+
+    #[test]
+    fn cell_600() {
+        check(&RegularPolytope::cell_600(), 120, 720, 600);
+    }
+}
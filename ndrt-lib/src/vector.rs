@@ -1,3 +1,4 @@
+use crate::matrix::Matrix;
 use crate::Float;
 use std::fmt::Display;
 
@@ -31,6 +32,8 @@ pub trait Vector:
     + std::ops::Sub<Float, Output = Self>
     + std::ops::Div<Float, Output = Self>
 {
+    type Matrix: Matrix<Self>;
+
     fn new() -> Self;
 
     fn from_iter(iter: impl Iterator<Item = Float>) -> Self;
@@ -39,6 +42,8 @@ pub trait Vector:
 
     fn components(&self) -> &[Float];
 
+    fn components_mut(&mut self) -> &mut [Float];
+
     fn sum_of_squares(&self) -> Float {
         self.components()
             .iter()
@@ -0,0 +1,391 @@
+use ndrt_lib::{Float, Matrix, Vector};
+
+// Axis aligned box around an object, empty if `min > max` on any axis
+#[derive(Debug, Clone, Copy)]
+pub struct Bounds<V: Vector> {
+    pub min: V,
+    pub max: V,
+}
+
+impl<V: Vector> Bounds<V> {
+    pub fn empty() -> Self {
+        Bounds {
+            min: V::pad(&[], Float::INFINITY),
+            max: V::pad(&[], -Float::INFINITY),
+        }
+    }
+
+    pub fn point(point: V) -> Self {
+        Bounds {
+            min: point,
+            max: point,
+        }
+    }
+
+    pub fn sphere(center: V, radius: Float) -> Self {
+        Bounds {
+            min: center - radius,
+            max: center + radius,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min
+            .components()
+            .iter()
+            .zip(self.max.components())
+            .any(|(min, max)| min > max)
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Bounds {
+            min: V::from_iter(
+                self.min
+                    .components()
+                    .iter()
+                    .zip(other.min.components())
+                    .map(|(a, b)| a.min(*b)),
+            ),
+            max: V::from_iter(
+                self.max
+                    .components()
+                    .iter()
+                    .zip(other.max.components())
+                    .map(|(a, b)| a.max(*b)),
+            ),
+        }
+    }
+
+    pub fn grow(&self, amount: Float) -> Self {
+        Bounds {
+            min: self.min - amount,
+            max: self.max + amount,
+        }
+    }
+
+    // Bounds of the box after an affine transformation `p' = matrix * p + translation`
+    pub fn transform(&self, matrix: &V::Matrix, translation: &V) -> Self {
+        let center = (self.min + self.max) / 2.0;
+        let half = (self.max - self.min) / 2.0;
+
+        let center = matrix.transform(&center) + *translation;
+        let half = V::from_iter(matrix.as_ref().iter().map(|row| {
+            row.components()
+                .iter()
+                .zip(half.components())
+                .map(|(m, h)| m.abs() * h)
+                .sum()
+        }));
+
+        Bounds {
+            min: center - half,
+            max: center + half,
+        }
+    }
+
+    fn centroid(&self) -> V {
+        (self.min + self.max) / 2.0
+    }
+
+    // Measure of the boundary of the box, proportional to the chance of a random ray
+    // hitting it. In N dimensions it is the sum of the volumes of the facets.
+    fn area(&self) -> Float {
+        let extent = self.max - self.min;
+        let extent = extent.components();
+
+        (0..extent.len())
+            .map(|skip| {
+                extent
+                    .iter()
+                    .enumerate()
+                    .filter(|(axis, _)| *axis != skip)
+                    .map(|(_, e)| e.max(0.0))
+                    .product::<Float>()
+            })
+            .sum()
+    }
+
+    // Range of the ray inside of the box, might start behind its origin
+    pub fn clip(&self, origin: &V, dir_inverse: &V) -> Option<(Float, Float)> {
+        let mut t_min = -Float::INFINITY;
+        let mut t_max = Float::INFINITY;
+
+        for (((o, inverse), min), max) in origin
+            .components()
+            .iter()
+            .zip(dir_inverse.components())
+            .zip(self.min.components())
+            .zip(self.max.components())
+        {
+            let t1 = (min - o) * inverse;
+            let t2 = (max - o) * inverse;
+
+            t_min = Float::max(t_min, Float::min(t1, t2));
+            t_max = Float::min(t_max, Float::max(t1, t2));
+        }
+
+        if t_min <= t_max {
+            Some((t_min, t_max))
+        } else {
+            None
+        }
+    }
+
+    // Where the ray enters the box if it does so before `t_max`, zero if it starts inside
+    fn enter(&self, origin: &V, dir_inverse: &V, t_max: Float) -> Option<Float> {
+        match self.clip(origin, dir_inverse) {
+            Some((t_enter, t_exit)) if t_exit > 0.0 && t_enter <= t_max => {
+                Some(Float::max(t_enter, 0.0))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    // Range of `Bvh::items`
+    Leaf(usize, usize),
+    // The first child directly follows its parent, this is the index of the second one
+    Inner(usize),
+}
+
+// Bounding volume hierarchy, split by the surface area heuristic (SAH)
+#[derive(Debug)]
+pub struct Bvh<V: Vector, T> {
+    nodes: Vec<(Bounds<V>, Node)>,
+    items: Vec<T>,
+}
+
+const BINS: usize = 16;
+const MAX_LEAF_SIZE: usize = 4;
+// Keeps the traversal stack of a fixed size
+const MAX_DEPTH: usize = 48;
+// Cost of visiting a node relative to testing an object
+const TRAVERSAL_COST: Float = 0.5;
+
+impl<V: Vector, T: Copy> Bvh<V, T> {
+    // Objects with empty bounds can never be hit and are left out
+    pub fn build(objects: Vec<(Bounds<V>, T)>) -> Self {
+        let mut objects: Vec<(Bounds<V>, V, T)> = objects
+            .into_iter()
+            .filter(|(bounds, _)| !bounds.is_empty())
+            .map(|(bounds, item)| (bounds, bounds.centroid(), item))
+            .collect();
+
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(objects.len() * 2),
+            items: Vec::with_capacity(objects.len()),
+        };
+
+        if !objects.is_empty() {
+            bvh.split(&mut objects, 0);
+        }
+
+        bvh
+    }
+
+    fn split(&mut self, objects: &mut [(Bounds<V>, V, T)], depth: usize) {
+        let bounds = objects
+            .iter()
+            .fold(Bounds::empty(), |a, (b, _, _)| a.union(b));
+        let index = self.nodes.len();
+
+        match find_split(objects, &bounds) {
+            Some(middle) if depth < MAX_DEPTH => {
+                self.nodes.push((bounds, Node::Inner(0)));
+
+                let (left, right) = objects.split_at_mut(middle);
+                self.split(left, depth + 1);
+
+                self.nodes[index].1 = Node::Inner(self.nodes.len());
+                self.split(right, depth + 1);
+            }
+            _ => {
+                let start = self.items.len();
+                self.items.extend(objects.iter().map(|(_, _, item)| *item));
+                self.nodes
+                    .push((bounds, Node::Leaf(start, self.items.len())));
+            }
+        }
+    }
+
+    // Calls `visit` for every object whose bounds are hit by the ray, nearer nodes first.
+    // It returns how far along the ray objects still matter, nodes behind are skipped.
+    pub fn traverse<F: FnMut(&T) -> Float>(&self, origin: &V, dir_inverse: &V, mut visit: F) {
+        let mut t_max = Float::INFINITY;
+
+        // Nodes to visit with the distance the ray enters them
+        let mut stack = [(0, 0.0); MAX_DEPTH + 1];
+        let mut size = 0;
+
+        if let Some((bounds, _)) = self.nodes.first() {
+            if let Some(t_enter) = bounds.enter(origin, dir_inverse, t_max) {
+                stack[0] = (0, t_enter);
+                size = 1;
+            }
+        }
+
+        while size > 0 {
+            size -= 1;
+            let (current, t_enter) = stack[size];
+
+            // Might be hidden by what was found since it was pushed
+            if t_enter > t_max {
+                continue;
+            }
+
+            match &self.nodes[current].1 {
+                Node::Leaf(start, end) => {
+                    for item in &self.items[*start..*end] {
+                        t_max = Float::min(t_max, visit(item));
+                        if t_max <= 0.0 {
+                            return;
+                        }
+                    }
+                }
+                Node::Inner(second) => {
+                    let enter = |child: usize| {
+                        let (bounds, _) = &self.nodes[child];
+                        bounds
+                            .enter(origin, dir_inverse, t_max)
+                            .map(|t_enter| (child, t_enter))
+                    };
+
+                    // The nearer child goes on top to be visited first
+                    let (near, far) = match (enter(current + 1), enter(*second)) {
+                        (Some(a), Some(b)) if b.1 < a.1 => (Some(b), Some(a)),
+                        (a, b) => (a, b),
+                    };
+
+                    for child in far.into_iter().chain(near) {
+                        stack[size] = child;
+                        size += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    // Depth first walk over the nodes `is_hit` accepts, calling `visit` for their objects.
+    // Both share `state`, e.g. to skip nodes once enough has been found.
+    pub fn walk<S, F: Fn(&S, &Bounds<V>) -> bool, G: FnMut(&mut S, &T)>(
+        &self,
+        state: &mut S,
+        is_hit: F,
+        mut visit: G,
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack = [0; MAX_DEPTH + 1];
+        let mut size = 1;
+
+        while size > 0 {
+            size -= 1;
+            let current = stack[size];
+            let (bounds, node) = &self.nodes[current];
+
+            if !is_hit(state, bounds) {
+                continue;
+            }
+
+            match node {
+                Node::Leaf(start, end) => {
+                    for item in &self.items[*start..*end] {
+                        visit(state, item);
+                    }
+                }
+                Node::Inner(second) => {
+                    stack[size] = *second;
+                    stack[size + 1] = current + 1;
+                    size += 2;
+                }
+            }
+        }
+    }
+}
+
+// Sorts the objects into two groups and returns the size of the first one, `None` if
+// it is cheaper to keep them together
+fn find_split<V: Vector, T>(
+    objects: &mut [(Bounds<V>, V, T)],
+    bounds: &Bounds<V>,
+) -> Option<usize> {
+    if objects.len() <= 1 {
+        return None;
+    }
+
+    let centroids = objects
+        .iter()
+        .fold(Bounds::empty(), |a, (_, c, _)| a.union(&Bounds::point(*c)));
+
+    // Bin along the axis the centroids are spread the most
+    let extent = centroids.max - centroids.min;
+    let (axis, width) = extent
+        .components()
+        .iter()
+        .cloned()
+        .enumerate()
+        .fold((0, 0.0), |a, b| if b.1 > a.1 { b } else { a });
+
+    if width <= 0.0 {
+        return None;
+    }
+
+    let offset = centroids.min.components()[axis];
+    let bin_of = |centroid: &V| {
+        let relative = (centroid.components()[axis] - offset) / width;
+        ((relative * BINS as Float) as usize).min(BINS - 1)
+    };
+
+    let mut bins = [(Bounds::<V>::empty(), 0); BINS];
+    for (object, centroid, _) in objects.iter() {
+        let bin = &mut bins[bin_of(centroid)];
+        bin.0 = bin.0.union(object);
+        bin.1 += 1;
+    }
+
+    // Costs of splitting after each bin, sweeping from both sides
+    let mut right_costs = [0.0; BINS];
+    let mut right = (Bounds::empty(), 0);
+    for bin in (1..BINS).rev() {
+        right = (right.0.union(&bins[bin].0), right.1 + bins[bin].1);
+        right_costs[bin - 1] = right.0.area() * right.1 as Float;
+    }
+
+    let mut best = (Float::INFINITY, 0);
+    let mut left = (Bounds::empty(), 0);
+    for bin in 0..BINS - 1 {
+        left = (left.0.union(&bins[bin].0), left.1 + bins[bin].1);
+        let cost = left.0.area() * left.1 as Float + right_costs[bin];
+
+        if left.1 > 0 && left.1 < objects.len() && cost < best.0 {
+            best = (cost, bin);
+        }
+    }
+
+    let area = bounds.area();
+    let leaf_cost = objects.len() as Float;
+    let split_cost = if area > 0.0 {
+        TRAVERSAL_COST + best.0 / area
+    } else {
+        TRAVERSAL_COST
+    };
+
+    if best.0 == Float::INFINITY || (objects.len() <= MAX_LEAF_SIZE && leaf_cost <= split_cost) {
+        return None;
+    }
+
+    // Partition in place, everything up to the best bin goes first
+    let mut middle = 0;
+    for index in 0..objects.len() {
+        if bin_of(&objects[index].1) <= best.1 {
+            objects.swap(index, middle);
+            middle += 1;
+        }
+    }
+
+    Some(middle)
+}
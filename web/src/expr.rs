@@ -0,0 +1,375 @@
+use ndrt_lib::Float;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<(Self, usize)> {
+        Some(match name {
+            "sin" => (Function::Sin, 1),
+            "cos" => (Function::Cos, 1),
+            "tan" => (Function::Tan, 1),
+            "abs" => (Function::Abs, 1),
+            "sqrt" => (Function::Sqrt, 1),
+            "exp" => (Function::Exp, 1),
+            "ln" => (Function::Ln, 1),
+            "min" => (Function::Min, 2),
+            "max" => (Function::Max, 2),
+            _ => return None,
+        })
+    }
+}
+
+// A parsed arithmetic expression over the variables x1, x2, ...
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Number(Float),
+    // Index of the variable, x1 is 0
+    Variable(usize),
+    Negate(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            index: 0,
+        };
+
+        let expr = parser.expression()?;
+
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("Unexpected {:?}", token)),
+        }
+    }
+
+    // Variables without a value are zero
+    pub fn evaluate(&self, variables: &[Float]) -> Float {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Variable(index) => variables.get(*index).copied().unwrap_or(0.0),
+            Expr::Negate(a) => -a.evaluate(variables),
+            Expr::Binary(operator, a, b) => {
+                let a = a.evaluate(variables);
+                let b = b.evaluate(variables);
+
+                match operator {
+                    Operator::Add => a + b,
+                    Operator::Subtract => a - b,
+                    Operator::Multiply => a * b,
+                    Operator::Divide => a / b,
+                    Operator::Power => a.powf(b),
+                }
+            }
+            Expr::Call(function, args) => {
+                let a = args[0].evaluate(variables);
+
+                match function {
+                    Function::Sin => a.sin(),
+                    Function::Cos => a.cos(),
+                    Function::Tan => a.tan(),
+                    Function::Abs => a.abs(),
+                    Function::Sqrt => a.sqrt(),
+                    Function::Exp => a.exp(),
+                    Function::Ln => a.ln(),
+                    Function::Min => a.min(args[1].evaluate(variables)),
+                    Function::Max => a.max(args[1].evaluate(variables)),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Float),
+    Name(String),
+    Symbol(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        let start = index;
+
+        if c.is_whitespace() {
+            index += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            while index < chars.len() && (chars[index].is_ascii_digit() || chars[index] == '.') {
+                index += 1;
+            }
+
+            let text: String = chars[start..index].iter().collect();
+            let value = text
+                .parse()
+                .map_err(|_| format!("Invalid number {}", text))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() {
+            while index < chars.len() && chars[index].is_ascii_alphanumeric() {
+                index += 1;
+            }
+
+            tokens.push(Token::Name(chars[start..index].iter().collect()));
+        } else if "+-*/^(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            index += 1;
+        } else {
+            return Err(format!("Unexpected character {:?}", c));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn accept(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        if self.accept(symbol) {
+            Ok(())
+        } else {
+            Err(format!("Expected {:?}", symbol))
+        }
+    }
+
+    // expression = term (("+" | "-") term)*
+    fn expression(&mut self) -> Result<Expr, String> {
+        let mut expr = self.term()?;
+
+        loop {
+            let operator = if self.accept('+') {
+                Operator::Add
+            } else if self.accept('-') {
+                Operator::Subtract
+            } else {
+                return Ok(expr);
+            };
+
+            expr = Expr::Binary(operator, Box::new(expr), Box::new(self.term()?));
+        }
+    }
+
+    // term = unary (("*" | "/") unary)*
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+
+        loop {
+            let operator = if self.accept('*') {
+                Operator::Multiply
+            } else if self.accept('/') {
+                Operator::Divide
+            } else {
+                return Ok(expr);
+            };
+
+            expr = Expr::Binary(operator, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    // unary = "-" unary | power
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.accept('-') {
+            Ok(Expr::Negate(Box::new(self.unary()?)))
+        } else {
+            self.power()
+        }
+    }
+
+    // power = atom ("^" unary)?, i.e. right associative and -x^2 is -(x^2)
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+
+        if self.accept('^') {
+            Ok(Expr::Binary(
+                Operator::Power,
+                Box::new(base),
+                Box::new(self.unary()?),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    // atom = number | name | name "(" arguments ")" | "(" expression ")"
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Symbol('(')) => {
+                let expr = self.expression()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some(Token::Name(name)) => {
+                if self.accept('(') {
+                    return self.call(&name);
+                }
+
+                match name.as_str() {
+                    "pi" => Ok(Expr::Number(std::f64::consts::PI as Float)),
+                    "e" => Ok(Expr::Number(std::f64::consts::E as Float)),
+                    _ => match name[1..].parse::<usize>() {
+                        Ok(index) if name.starts_with('x') && index > 0 => {
+                            Ok(Expr::Variable(index - 1))
+                        }
+                        _ => Err(format!("Unknown variable {}", name)),
+                    },
+                }
+            }
+            Some(token) => Err(format!("Unexpected {:?}", token)),
+            None => Err("Unexpected end of the expression".to_owned()),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Expr, String> {
+        let (function, arity) =
+            Function::from_name(name).ok_or_else(|| format!("Unknown function {}", name))?;
+
+        let mut args = vec![self.expression()?];
+        while self.accept(',') {
+            args.push(self.expression()?);
+        }
+        self.expect(')')?;
+
+        if args.len() != arity {
+            return Err(format!(
+                "{} takes {} argument(s) but got {}",
+                name,
+                arity,
+                args.len()
+            ));
+        }
+
+        Ok(Expr::Call(function, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: Float = 1e-5;
+
+    fn evaluate(source: &str, variables: &[Float]) -> Float {
+        Expr::parse(source).unwrap().evaluate(variables)
+    }
+
+    fn assert_evaluates(source: &str, variables: &[Float], expected: Float) {
+        let value = evaluate(source, variables);
+        assert!(
+            (value - expected).abs() < EPSILON,
+            "{} is {} instead of {}",
+            source,
+            value,
+            expected
+        );
+    }
+
+    fn assert_error(source: &str, message: &str) {
+        match Expr::parse(source) {
+            Ok(expr) => panic!("{} parsed as {:?}", source, expr),
+            Err(error) => assert!(
+                error.contains(message),
+                "{} failed with {:?} instead of {:?}",
+                source,
+                error,
+                message
+            ),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_evaluates("1 + 2 * 3", &[], 7.0);
+        assert_evaluates("(1 + 2) * 3", &[], 9.0);
+        assert_evaluates("2 * 3 ^ 2", &[], 18.0);
+        assert_evaluates("5 - 2 - 1", &[], 2.0);
+        assert_evaluates("8 / 2 / 2", &[], 2.0);
+        assert_evaluates("1 - -2", &[], 3.0);
+    }
+
+    #[test]
+    fn power_is_right_associative() {
+        assert_evaluates("2 ^ 3 ^ 2", &[], 512.0);
+        assert_evaluates("2 ^ -1", &[], 0.5);
+    }
+
+    #[test]
+    fn negation_binds_weaker_than_power() {
+        assert_evaluates("-x1^2", &[3.0], -9.0);
+        assert_evaluates("-(x1^2)", &[3.0], -9.0);
+        assert_evaluates("(-x1)^2", &[3.0], 9.0);
+    }
+
+    #[test]
+    fn variables_and_constants() {
+        let variables: Vec<Float> = (1..=9).map(|i| i as Float).collect();
+        assert_evaluates("x1 + 10 * x2 + 100 * x9", &variables, 921.0);
+        assert_evaluates("x12", &variables, 0.0);
+        assert_evaluates("pi", &[], std::f64::consts::PI as Float);
+        assert_evaluates("e", &[], std::f64::consts::E as Float);
+        assert_evaluates("max(sin(pi / 2), ln(e) / 2)", &[], 1.0);
+    }
+
+    #[test]
+    fn errors() {
+        assert_error("sin(1, 2)", "sin takes 1 argument(s) but got 2");
+        assert_error("min(1)", "min takes 2 argument(s) but got 1");
+        assert_error("foo(1)", "Unknown function foo");
+        assert_error("y + 1", "Unknown variable y");
+        assert_error("x0", "Unknown variable x0");
+        assert_error("1 2", "Unexpected Number(2.0)");
+        assert_error("x1)", "Unexpected Symbol(')')");
+        assert_error("(1 + 2", "Expected ')'");
+        assert_error("1 +", "Unexpected end");
+        assert_error("1 # 2", "Unexpected character '#'");
+        assert_error("1.2.3", "Invalid number 1.2.3");
+    }
+}
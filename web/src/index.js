@@ -235,6 +235,21 @@ async function updateScene() {
   await render.start({ dimension, scene });
 }
 
+// Compares the acceleration structures on all scenes, run `benchmark()` in the console
+window.benchmark = async function(dimension = render.dimension, size = 288) {
+  await render.stop();
+
+  const scenes = Array.from(sceneEl.options).map(option => option.value);
+  const results = await render.workerPool.send(
+    render.workerPool.pool[0],
+    "benchmark",
+    { dimension, scenes, camPos: render.camPos, size }
+  );
+  console.table(results);
+
+  render.resume();
+};
+
 dimensionsEl.addEventListener("change", updateScene);
 sceneEl.addEventListener("change", updateScene);
 
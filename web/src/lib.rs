@@ -1,7 +1,10 @@
 extern crate ndrt_lib;
 extern crate wasm_bindgen;
 
+mod bvh;
 mod color;
+mod expr;
+mod sdf;
 mod tracer;
 mod world;
 
@@ -9,7 +12,7 @@ use wasm_bindgen::prelude::*;
 
 use color::{Color, ColorInt};
 use ndrt_lib::{FixedVector, Float, Vector};
-use tracer::{sample, DimensionalWorld};
+use tracer::{sample, sample_packet, Camera, DimensionalWorld, View, PACKET_SIZE};
 use world::World;
 
 #[wasm_bindgen]
@@ -107,28 +110,65 @@ fn test_deviation(
     false
 }
 
-fn init_sample_grid<V: Vector>(
-    data: &mut wasm_bindgen::Clamped<Vec<u8>>,
-    world: &DimensionalWorld<V>,
+// The rows `start..end` of a picture, which is centered in a square of `min_dim` pixels
+// spanning the relative coordinates from 0 to 1
+#[derive(Clone, Copy)]
+struct Frame {
     start: isize,
     end: isize,
     width: isize,
     height: isize,
     min_dim: Float,
+}
+
+impl Frame {
+    fn rel_x(&self, x: isize) -> Float {
+        (x as Float + (self.min_dim - self.width as Float) / 2.0) / self.min_dim
+    }
+
+    fn rel_y(&self, y: isize) -> Float {
+        1.0 - (y as Float + (self.min_dim - self.height as Float) / 2.0) / self.min_dim
+    }
+}
+
+fn init_sample_grid<V: Vector>(
+    data: &mut wasm_bindgen::Clamped<Vec<u8>>,
+    world: &DimensionalWorld<V>,
+    view: &View<V>,
+    frame: Frame,
     step: isize,
+    packets: bool,
 ) {
-    let offset_x = (min_dim - width as Float) / 2.0;
-    let offset_y = (min_dim - height as Float) / 2.0;
+    let Frame {
+        start, end, width, ..
+    } = frame;
 
     let step_offset = step / 2;
-    for step_y in (start + step_offset..end - step_offset).step_by(step as usize) {
-        let rel_y = 1.0 - (step_y as Float + offset_y) / min_dim;
+    let columns: Vec<isize> = (step_offset..width - step_offset)
+        .step_by(step as usize)
+        .collect();
 
-        for step_x in (step_offset..width - step_offset).step_by(step as usize) {
-            let rel_x = (step_x as Float + offset_x) / min_dim;
+    for step_y in (start + step_offset..end - step_offset).step_by(step as usize) {
+        let rel_y = frame.rel_y(step_y);
+
+        // Neighbouring samples of a row are traced together, the rest one by one
+        for chunk in columns.chunks(PACKET_SIZE) {
+            if packets && chunk.len() == PACKET_SIZE {
+                let mut points = [(0.0, rel_y); PACKET_SIZE];
+                for (point, step_x) in points.iter_mut().zip(chunk) {
+                    point.0 = frame.rel_x(*step_x);
+                }
 
-            let color = sample::<V>(&world, rel_x, rel_y).to_int();
-            set_px(data, start, width, step_x, step_y, color);
+                let colors = sample_packet::<V>(world, view, points);
+                for (color, step_x) in colors.iter().zip(chunk) {
+                    set_px(data, start, width, *step_x, step_y, color.to_int());
+                }
+            } else {
+                for step_x in chunk {
+                    let color = sample::<V>(world, view, frame.rel_x(*step_x), rel_y).to_int();
+                    set_px(data, start, width, *step_x, step_y, color);
+                }
+            }
         }
     }
 }
@@ -136,17 +176,19 @@ fn init_sample_grid<V: Vector>(
 fn fill_sample_grid<V: Vector>(
     data: &mut wasm_bindgen::Clamped<Vec<u8>>,
     world: &DimensionalWorld<V>,
-    start: isize,
-    end: isize,
-    width: isize,
-    height: isize,
-    min_dim: Float,
+    view: &View<V>,
+    frame: Frame,
     step: isize,
     substep: isize,
     deviation_threshold: Float,
 ) {
-    let offset_x = (min_dim - width as Float) / 2.0;
-    let offset_y = (min_dim - height as Float) / 2.0;
+    let Frame {
+        start,
+        end,
+        width,
+        height,
+        ..
+    } = frame;
 
     // NOTE: offset is floored!
     let step_offset = step / 2;
@@ -165,18 +207,18 @@ fn fill_sample_grid<V: Vector>(
                 ..(step_x + step_offset + 1))
                 .step_by(substep as usize);
 
-            let center_int = get_px(&data, start, width, step_x, step_y);
+            let center_int = get_px(data, start, width, step_x, step_y);
             let center = Color::from_int(&center_int);
-            let top = get_px_checked(&data, start, width, step_x, step_y - step)
+            let top = get_px_checked(data, start, width, step_x, step_y - step)
                 .as_ref()
                 .map(Color::from_int);
-            let right = get_px_checked(&data, start, width, step_x + step, step_y)
+            let right = get_px_checked(data, start, width, step_x + step, step_y)
                 .as_ref()
                 .map(Color::from_int);
-            let bottom = get_px_checked(&data, start, width, step_x, step_y + step)
+            let bottom = get_px_checked(data, start, width, step_x, step_y + step)
                 .as_ref()
                 .map(Color::from_int);
-            let left = get_px_checked(&data, start, width, step_x - step, step_y)
+            let left = get_px_checked(data, start, width, step_x - step, step_y)
                 .as_ref()
                 .map(Color::from_int);
 
@@ -185,7 +227,7 @@ fn fill_sample_grid<V: Vector>(
                 || test_deviation(center, top, bottom, left, right, deviation_threshold);
 
             for substep_y in substep_range_y.clone() {
-                let rel_y = 1.0 - (substep_y as Float + offset_y) / min_dim;
+                let rel_y = frame.rel_y(substep_y);
 
                 for substep_x in substep_range_x.clone() {
                     if substep_x == step_x && substep_y == step_y {
@@ -193,10 +235,10 @@ fn fill_sample_grid<V: Vector>(
                         continue;
                     }
 
-                    let rel_x = (substep_x as Float + offset_x) / min_dim;
+                    let rel_x = frame.rel_x(substep_x);
 
                     let color = if resample {
-                        sample::<V>(&world, rel_x, rel_y).to_int()
+                        sample::<V>(world, view, rel_x, rel_y).to_int()
                     } else {
                         center_int
                     };
@@ -208,35 +250,92 @@ fn fill_sample_grid<V: Vector>(
     }
 }
 
-fn update_n<V: Vector>(
+// Traces the grid of samples every ninth pixel and, if `refine`, fills in the pixels
+// between them
+fn update_n<V: Vector + 'static>(
     mut data: wasm_bindgen::Clamped<Vec<u8>>,
     world: &World,
     cam_pos: Vec<Float>,
+    frame: Frame,
+    refine: bool,
+) -> wasm_bindgen::Clamped<Vec<u8>> {
+    let camera = Camera {
+        position: V::pad(&cam_pos, -8.0),
+        center: V::new(),
+    };
+    let packets = world.packet_tracing;
+    let world = DimensionalWorld::<V>::cached(world);
+
+    let Frame {
+        start, end, width, ..
+    } = frame;
+
+    // Only objects seen in the rows of this chunk are traced
+    let view = View::new(
+        &world,
+        camera,
+        (frame.rel_x(0), frame.rel_x(width)),
+        (frame.rel_y(end), frame.rel_y(start)),
+    );
+
+    init_sample_grid::<V>(&mut data, &world, &view, frame, 9, packets);
+    if refine {
+        // fill_sample_grid::<V>(&mut data, &world, &view, frame, 27, 9, 0.05);
+        fill_sample_grid::<V>(&mut data, &world, &view, frame, 9, 3, 0.05);
+        fill_sample_grid::<V>(&mut data, &world, &view, frame, 3, 1, 0.1);
+    }
+
+    data
+}
+
+fn update_dimension(
+    data: wasm_bindgen::Clamped<Vec<u8>>,
+    world: &World,
+    cam_pos: Vec<Float>,
+    frame: Frame,
+    dimension: usize,
+    refine: bool,
+) -> wasm_bindgen::Clamped<Vec<u8>> {
+    match dimension {
+        2 => update_n::<FixedVector<2>>(data, world, cam_pos, frame, refine),
+        3 => update_n::<FixedVector<3>>(data, world, cam_pos, frame, refine),
+        4 => update_n::<FixedVector<4>>(data, world, cam_pos, frame, refine),
+        5 => update_n::<FixedVector<5>>(data, world, cam_pos, frame, refine),
+        6 => update_n::<FixedVector<6>>(data, world, cam_pos, frame, refine),
+        7 => update_n::<FixedVector<7>>(data, world, cam_pos, frame, refine),
+        8 => update_n::<FixedVector<8>>(data, world, cam_pos, frame, refine),
+        9 => update_n::<FixedVector<9>>(data, world, cam_pos, frame, refine),
+        _ => data,
+    }
+}
+
+#[wasm_bindgen]
+pub fn update(
+    data: wasm_bindgen::Clamped<Vec<u8>>,
+    world: &World,
+    cam_pos: Vec<Float>,
     start: isize,
     end: isize,
     width: isize,
     height: isize,
     min_dim: Float,
+    dimension: usize,
 ) -> wasm_bindgen::Clamped<Vec<u8>> {
-    let cam_pos = V::pad(&cam_pos, -8.0);
-    let world = DimensionalWorld::from_world(world, cam_pos);
-
-    init_sample_grid::<V>(&mut data, &world, start, end, width, height, min_dim, 9);
-    // fill_sample_grid::<V>(
-    //     &mut data, &world, start, end, width, height, min_dim, 27, 9, 0.05,
-    // );
-    fill_sample_grid::<V>(
-        &mut data, &world, start, end, width, height, min_dim, 9, 3, 0.05,
-    );
-    fill_sample_grid::<V>(
-        &mut data, &world, start, end, width, height, min_dim, 3, 1, 0.1,
-    );
-
-    data
+    let frame = Frame {
+        start,
+        end,
+        width,
+        height,
+        min_dim,
+    };
+
+    update_dimension(data, world, cam_pos, frame, dimension, true)
 }
 
+/// Like `update`, but only traces the samples every ninth pixel that the rest of the
+/// picture is filled in from. These are the only rays traced in packets.
 #[wasm_bindgen]
-pub fn update(
+pub fn update_sample_grid(
     data: wasm_bindgen::Clamped<Vec<u8>>,
     world: &World,
     cam_pos: Vec<Float>,
@@ -247,15 +346,13 @@ pub fn update(
     min_dim: Float,
     dimension: usize,
 ) -> wasm_bindgen::Clamped<Vec<u8>> {
-    match dimension {
-        2 => update_n::<FixedVector<2>>(data, world, cam_pos, start, end, width, height, min_dim),
-        3 => update_n::<FixedVector<3>>(data, world, cam_pos, start, end, width, height, min_dim),
-        4 => update_n::<FixedVector<4>>(data, world, cam_pos, start, end, width, height, min_dim),
-        5 => update_n::<FixedVector<5>>(data, world, cam_pos, start, end, width, height, min_dim),
-        6 => update_n::<FixedVector<6>>(data, world, cam_pos, start, end, width, height, min_dim),
-        7 => update_n::<FixedVector<7>>(data, world, cam_pos, start, end, width, height, min_dim),
-        8 => update_n::<FixedVector<8>>(data, world, cam_pos, start, end, width, height, min_dim),
-        9 => update_n::<FixedVector<9>>(data, world, cam_pos, start, end, width, height, min_dim),
-        _ => data,
-    }
+    let frame = Frame {
+        start,
+        end,
+        width,
+        height,
+        min_dim,
+    };
+
+    update_dimension(data, world, cam_pos, frame, dimension, false)
 }
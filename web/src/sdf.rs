@@ -0,0 +1,508 @@
+use crate::world::SdfNode;
+use ndrt_lib::{Float, RegularPolytope, Vector};
+
+#[derive(Debug)]
+enum Node<V: Vector> {
+    Sphere(V, Float),
+    Cube(V, Float),
+    Capsule(V, V, Float),
+    // Unit normal and offset
+    Plane(V, Float),
+    // Center, half of the size and depth
+    Menger(V, Float, usize),
+    // Vertices and facets (unit normal and offset) of the simplex relative to
+    // its center, and the depth
+    Sierpinski(V, Vec<V>, Vec<(V, Float)>, usize),
+    Union(usize, usize),
+    Intersection(usize, usize),
+    Difference(usize, usize),
+    SmoothUnion(usize, usize, Float),
+    Round(usize, Float),
+    Twist(usize, Float),
+}
+
+// Signed distance field of a tree of nodes, the last node is the root
+#[derive(Debug)]
+pub struct SdfTree<V: Vector> {
+    nodes: Vec<Node<V>>,
+}
+
+impl<V: Vector> SdfTree<V> {
+    pub fn from_world(nodes: &[SdfNode]) -> Self {
+        let nodes = nodes
+            .iter()
+            .map(|node| match node {
+                SdfNode::Sphere { center, radius } => Node::Sphere(V::pad(center, 0.0), *radius),
+                SdfNode::Cube { center, size } => Node::Cube(V::pad(center, 0.0), size / 2.0),
+                SdfNode::Capsule { from, to, radius } => {
+                    Node::Capsule(V::pad(from, 0.0), V::pad(to, 0.0), *radius)
+                }
+                SdfNode::Plane { normal, offset } => {
+                    let normal = V::pad(normal, 0.0);
+                    let length = normal.length();
+                    if length > 0.0 {
+                        Node::Plane(normal / length, offset / length)
+                    } else {
+                        // Either everything or nothing is inside
+                        Node::Plane(normal, offset.signum() * Float::INFINITY)
+                    }
+                }
+                SdfNode::Menger {
+                    center,
+                    size,
+                    depth,
+                } => Node::Menger(V::pad(center, 0.0), size / 2.0, *depth as usize),
+                SdfNode::Sierpinski {
+                    center,
+                    radius,
+                    depth,
+                } => {
+                    let dimension = V::new().components().len();
+                    let simplex = RegularPolytope::simplex(dimension);
+
+                    Node::Sierpinski(
+                        V::pad(center, 0.0),
+                        simplex
+                            .vertices
+                            .iter()
+                            .map(|v| V::pad(v, 0.0) * *radius)
+                            .collect(),
+                        simplex
+                            .facets
+                            .iter()
+                            .map(|(normal, offset)| (V::pad(normal, 0.0), offset * radius))
+                            .collect(),
+                        *depth as usize,
+                    )
+                }
+                SdfNode::Union(a, b) => Node::Union(*a as usize, *b as usize),
+                SdfNode::Intersection(a, b) => Node::Intersection(*a as usize, *b as usize),
+                SdfNode::Difference(a, b) => Node::Difference(*a as usize, *b as usize),
+                SdfNode::SmoothUnion(a, b, k) => Node::SmoothUnion(*a as usize, *b as usize, *k),
+                SdfNode::Round(a, r) => Node::Round(*a as usize, *r),
+                SdfNode::Twist(a, rate) => Node::Twist(*a as usize, *rate),
+            })
+            .collect();
+
+        Self { nodes }
+    }
+
+    pub fn distance(&self, point: &V) -> Float {
+        if self.nodes.is_empty() {
+            return Float::INFINITY;
+        }
+
+        self.node_distance(self.nodes.len() - 1, point)
+    }
+
+    // Bounding sphere, `None` if the shape is infinite
+    pub fn bounds(&self) -> Option<(V, Float)> {
+        if self.nodes.is_empty() {
+            return Some((V::new(), 0.0));
+        }
+
+        self.node_bounds(self.nodes.len() - 1)
+    }
+
+    fn node_distance(&self, index: usize, p: &V) -> Float {
+        match &self.nodes[index] {
+            Node::Sphere(center, radius) => (*p - *center).length() - radius,
+            Node::Cube(center, half_size) => {
+                let mut outside: Float = 0.0;
+                let mut inside = -Float::INFINITY;
+
+                for (p, c) in p.components().iter().zip(center.components()) {
+                    let q = (p - c).abs() - half_size;
+                    outside += Float::max(q, 0.0) * Float::max(q, 0.0);
+                    inside = Float::max(inside, q);
+                }
+
+                outside.sqrt() + Float::min(inside, 0.0)
+            }
+            Node::Capsule(from, to, radius) => {
+                let from_to_p = *p - *from;
+                let from_to_to = *to - *from;
+                let length_squared = from_to_to.sum_of_squares();
+                let h = if length_squared > 0.0 {
+                    (from_to_p.dot(&from_to_to) / length_squared).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+
+                (from_to_p - from_to_to * h).length() - radius
+            }
+            Node::Plane(normal, offset) => normal.dot(p) - offset,
+            Node::Menger(center, half_size, depth) => {
+                menger_distance(&((*p - *center) / *half_size), *depth) * half_size
+            }
+            Node::Sierpinski(center, vertices, facets, depth) => {
+                sierpinski_distance(&(*p - *center), vertices, facets, *depth)
+            }
+            Node::Union(a, b) => Float::min(self.node_distance(*a, p), self.node_distance(*b, p)),
+            Node::Intersection(a, b) => {
+                Float::max(self.node_distance(*a, p), self.node_distance(*b, p))
+            }
+            Node::Difference(a, b) => {
+                Float::max(self.node_distance(*a, p), -self.node_distance(*b, p))
+            }
+            Node::SmoothUnion(a, b, k) => {
+                let a = self.node_distance(*a, p);
+                let b = self.node_distance(*b, p);
+
+                if *k <= 0.0 {
+                    return Float::min(a, b);
+                }
+
+                // Polynomial smooth minimum
+                let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+                b * (1.0 - h) + a * h - k * h * (1.0 - h)
+            }
+            Node::Round(a, radius) => self.node_distance(*a, p) - radius,
+            Node::Twist(a, rate) => {
+                let components = p.components();
+                if components.len() < 3 {
+                    return self.node_distance(*a, p);
+                }
+
+                let angle = rate * components[2];
+                let (sin, cos) = angle.sin_cos();
+                let (x, y) = (components[0], components[1]);
+
+                let mut twisted = *p;
+                twisted.components_mut()[0] = cos * x - sin * y;
+                twisted.components_mut()[1] = sin * x + cos * y;
+
+                // The twist stretches space, so we have to scale down the
+                // distance to still have a lower bound
+                let stretch = Float::sqrt(1.0 + rate * rate * (x * x + y * y));
+                self.node_distance(*a, &twisted) / stretch
+            }
+        }
+    }
+
+    fn node_bounds(&self, index: usize) -> Option<(V, Float)> {
+        match &self.nodes[index] {
+            Node::Sphere(center, radius) => Some((*center, *radius)),
+            Node::Cube(center, half_size) => {
+                let dimension = center.components().len() as Float;
+                Some((*center, half_size * dimension.sqrt()))
+            }
+            Node::Capsule(from, to, radius) => {
+                let center = (*from + *to) / 2.0;
+                Some((center, (*to - *from).length() / 2.0 + radius))
+            }
+            Node::Menger(center, half_size, _) => {
+                let dimension = center.components().len() as Float;
+                Some((*center, half_size * dimension.sqrt()))
+            }
+            Node::Sierpinski(center, vertices, _, _) => {
+                Some((*center, vertices.first().map_or(0.0, |v| v.length())))
+            }
+            Node::Plane(_, _) => None,
+            Node::Union(a, b) | Node::SmoothUnion(a, b, _) => {
+                let (a_center, a_radius) = self.node_bounds(*a)?;
+                let (b_center, b_radius) = self.node_bounds(*b)?;
+                let distance = (b_center - a_center).length();
+
+                if distance + b_radius <= a_radius {
+                    Some((a_center, a_radius))
+                } else if distance + a_radius <= b_radius {
+                    Some((b_center, b_radius))
+                } else {
+                    let radius = (distance + a_radius + b_radius) / 2.0;
+                    let center =
+                        a_center + (b_center - a_center) * ((radius - a_radius) / distance);
+                    Some((center, radius))
+                }
+                .map(|(center, radius)| match &self.nodes[index] {
+                    // Blending can grow the shape a bit
+                    Node::SmoothUnion(_, _, k) => (center, radius + k.max(0.0)),
+                    _ => (center, radius),
+                })
+            }
+            Node::Intersection(a, b) => match (self.node_bounds(*a), self.node_bounds(*b)) {
+                (Some(a), Some(b)) => Some(if a.1 < b.1 { a } else { b }),
+                (a, b) => a.or(b),
+            },
+            Node::Difference(a, _) => self.node_bounds(*a),
+            Node::Round(a, radius) => self
+                .node_bounds(*a)
+                .map(|(center, r)| (center, r + radius.max(0.0))),
+            Node::Twist(a, _) => self.node_bounds(*a).map(|(center, radius)| {
+                // Rotating around the z axis, the center might end up anywhere on its circle
+                let mut axis_center = center;
+                let components = axis_center.components_mut();
+                let offset =
+                    Float::sqrt(components[0] * components[0] + components[1] * components[1]);
+                components[0] = 0.0;
+                components[1] = 0.0;
+
+                (axis_center, radius + offset)
+            }),
+        }
+    }
+}
+
+// Distance to the sponge in the cube from -1 to 1
+fn menger_distance<V: Vector>(p: &V, depth: usize) -> Float {
+    let mut distance: Float = 0.0;
+    let mut inside = -Float::INFINITY;
+    for c in p.components() {
+        let q = c.abs() - 1.0;
+        distance += Float::max(q, 0.0) * Float::max(q, 0.0);
+        inside = Float::max(inside, q);
+    }
+    let mut distance = distance.sqrt() + Float::min(inside, 0.0);
+
+    // Half of the size of the cells at the current level
+    let mut cell = 1.0;
+
+    for _ in 0..depth {
+        cell /= 3.0;
+
+        // Distances to the middle thirds of the cells (negative inside of them),
+        // a hole is where two of them overlap
+        let mut smallest = Float::INFINITY;
+        let mut second = Float::INFINITY;
+
+        for c in p.components() {
+            let local = (c + 3.0 * cell).rem_euclid(6.0 * cell) - 3.0 * cell;
+            let d = local.abs() - cell;
+
+            if d < smallest {
+                second = smallest;
+                smallest = d;
+            } else if d < second {
+                second = d;
+            }
+        }
+
+        distance = Float::max(distance, -second);
+    }
+
+    distance
+}
+
+// Folds the point into the copy at the nearest vertex until we reach the
+// smallest simplices
+fn sierpinski_distance<V: Vector>(
+    p: &V,
+    vertices: &[V],
+    facets: &[(V, Float)],
+    depth: usize,
+) -> Float {
+    let mut p = *p;
+    let mut scale = 1.0;
+
+    for _ in 0..depth {
+        let nearest = vertices
+            .iter()
+            .min_by(|a, b| {
+                let a = (p - **a).sum_of_squares();
+                let b = (p - **b).sum_of_squares();
+                a.partial_cmp(&b).unwrap()
+            })
+            .unwrap();
+
+        p = p * 2.0 - *nearest;
+        scale *= 2.0;
+    }
+
+    let distance = facets
+        .iter()
+        .map(|(normal, offset)| normal.dot(&p) - offset)
+        .fold(-Float::INFINITY, Float::max);
+
+    distance / scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndrt_lib::FixedVector;
+
+    type V = FixedVector<3>;
+
+    const EPSILON: Float = 1e-4;
+
+    fn sphere(center: Vec<Float>, radius: Float) -> SdfNode {
+        SdfNode::Sphere { center, radius }
+    }
+
+    // The distance is a lower bound if no point closer than it to a point of a grid
+    // around the shape is on the other side of the surface
+    fn assert_lower_bound<W: Vector>(distance: impl Fn(&W) -> Float, inside: impl Fn(&W) -> bool) {
+        let dimension = W::new().components().len();
+        let diagonal = W::pad(&[], 1.0) / (dimension as Float).sqrt();
+        let mut directions = vec![diagonal, diagonal * -1.0];
+        for axis in 0..dimension {
+            let mut direction = W::new();
+            direction.components_mut()[axis] = 1.0;
+            directions.extend([direction, direction * -1.0]);
+        }
+
+        // Off the planes of symmetry, where points are on the surface
+        let steps: usize = 5;
+        for index in 0..steps.pow(dimension as u32) {
+            let p = W::from_iter((0..dimension).map(|axis| {
+                let step = index / steps.pow(axis as u32) % steps;
+                -1.1 + 0.53 * step as Float
+            }));
+            let d = distance(&p);
+            if d.abs() < EPSILON {
+                continue;
+            }
+            assert_eq!(d < 0.0, inside(&p));
+
+            for direction in &directions {
+                for fraction in [0.25, 0.5, 0.75, 0.99] {
+                    let q = p + *direction * (d.abs() * fraction);
+                    assert_eq!(inside(&q), d < 0.0);
+                }
+            }
+        }
+    }
+
+    // In the sponge unless two coordinates are in the middle third at some level
+    fn in_menger<W: Vector>(p: &W, depth: usize) -> bool {
+        let c = p.components();
+        c.iter().all(|c| c.abs() <= 1.0)
+            && (1..=depth).all(|level| {
+                let cells = Float::powi(3.0, level as i32);
+                let middle = c
+                    .iter()
+                    .filter(|c| ((*c + 1.0) / 2.0 * cells).floor() as i64 % 3 == 1)
+                    .count();
+                middle < 2
+            })
+    }
+
+    fn menger_in_dimension<W: Vector>() {
+        let depth = 2;
+
+        // A point in the hole through the middle is a third away from its walls, a point
+        // in the corner is closest to the faces of the cube
+        let hole = menger_distance(&W::pad(&[0.0, 0.0], 0.9), depth);
+        assert!((hole - 1.0 / 3.0).abs() < EPSILON);
+        let solid = menger_distance(&W::pad(&[], 0.9), depth);
+        assert!((solid + 0.1).abs() < EPSILON);
+
+        assert_lower_bound(|p: &W| menger_distance(p, depth), |p| in_menger(p, depth));
+    }
+
+    #[test]
+    fn menger_sponges() {
+        menger_in_dimension::<V>();
+        menger_in_dimension::<FixedVector<4>>();
+        menger_in_dimension::<FixedVector<5>>();
+    }
+
+    // In one of the copies at the vertices, recursively. Copies that are farther away
+    // than the circumradius can't contain the point.
+    fn in_sierpinski<W: Vector>(
+        p: &W,
+        vertices: &[W],
+        facets: &[(W, Float)],
+        depth: usize,
+    ) -> bool {
+        if depth == 0 {
+            return facets
+                .iter()
+                .all(|(normal, offset)| normal.dot(p) <= *offset);
+        }
+
+        let radius_squared = vertices[0].sum_of_squares();
+        vertices
+            .iter()
+            .map(|v| *p * 2.0 - *v)
+            .filter(|q| q.sum_of_squares() <= radius_squared)
+            .any(|q| in_sierpinski(&q, vertices, facets, depth - 1))
+    }
+
+    fn sierpinski_in_dimension<W: Vector>() {
+        let depth = 2;
+        let tree = SdfTree::<W>::from_world(&[SdfNode::Sierpinski {
+            center: vec![],
+            radius: 1.0,
+            depth: depth as u32,
+        }]);
+        let (vertices, facets) = match &tree.nodes[0] {
+            Node::Sierpinski(_, vertices, facets, _) => (vertices, facets),
+            _ => unreachable!(),
+        };
+
+        // The middle is left out from the first level on, close to a vertex is solid
+        assert!(tree.distance(&W::new()) > 0.0);
+        assert!(tree.distance(&(vertices[0] * 0.9)) < 0.0);
+
+        assert_lower_bound(
+            |p: &W| tree.distance(p),
+            |p| in_sierpinski(p, vertices, facets, depth),
+        );
+    }
+
+    #[test]
+    fn sierpinski_simplices() {
+        sierpinski_in_dimension::<V>();
+        sierpinski_in_dimension::<FixedVector<4>>();
+        sierpinski_in_dimension::<FixedVector<5>>();
+    }
+
+    #[test]
+    fn union_of_spheres() {
+        let tree = SdfTree::<V>::from_world(&[
+            sphere(vec![-2.0], 1.0),
+            sphere(vec![2.0], 1.0),
+            SdfNode::Union(0, 1),
+            SdfNode::Round(2, 0.5),
+        ]);
+
+        assert!((tree.distance(&V::new()) - 0.5).abs() < EPSILON);
+        assert!((tree.distance(&V::pad(&[2.0], 0.0)) + 1.5).abs() < EPSILON);
+        assert!((tree.distance(&V::pad(&[-2.0, 3.0], 0.0)) - 1.5).abs() < EPSILON);
+
+        let (center, radius) = tree.bounds().unwrap();
+        assert!(center.length() < EPSILON);
+        assert!((radius - 3.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn sphere_carved_out_of_cube() {
+        let tree = SdfTree::<V>::from_world(&[
+            SdfNode::Cube {
+                center: vec![],
+                size: 2.0,
+            },
+            sphere(vec![], 0.5),
+            SdfNode::Difference(0, 1),
+        ]);
+
+        // In the hole, inside the wall and outside of the cube
+        assert!((tree.distance(&V::new()) - 0.5).abs() < EPSILON);
+        assert!((tree.distance(&V::pad(&[0.75], 0.0)) + 0.25).abs() < EPSILON);
+        assert!((tree.distance(&V::pad(&[3.0], 0.0)) - 2.0).abs() < EPSILON);
+
+        // The bounds of the cube, the hole does not change them
+        let (center, radius) = tree.bounds().unwrap();
+        assert!(center.length() < EPSILON);
+        assert!((radius - Float::sqrt(3.0)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn planes_are_unbounded() {
+        let tree = SdfTree::<V>::from_world(&[
+            sphere(vec![], 1.0),
+            SdfNode::Plane {
+                normal: vec![0.0, 0.0, 2.0],
+                offset: 0.0,
+            },
+            SdfNode::Intersection(0, 1),
+            SdfNode::Union(0, 1),
+        ]);
+
+        // Half of the ball is left of the intersection, but the union is unbounded
+        assert!((tree.node_distance(2, &V::pad(&[0.0, 0.0, 0.5], 0.0)) - 0.5).abs() < EPSILON);
+        assert!(tree.node_bounds(2).is_some());
+        assert!(tree.bounds().is_none());
+    }
+}
@@ -1,6 +1,16 @@
+use crate::bvh::{Bounds, Bvh};
 use crate::color::Color;
-use crate::world::{Light, Sphere, Surface, World};
-use ndrt_lib::{Float, Vector};
+use crate::expr::Expr;
+use crate::sdf::SdfTree;
+use crate::world::CsgNode as WorldCsgNode;
+use crate::world::{
+    Acceleration, Cube, Light, Metaballs, Sphere, Surface, TorusKind, Transform, TransformStep,
+    World,
+};
+use ndrt_lib::{FixedVector, Float, Matrix, Vector};
+use std::collections::HashMap;
+use std::iter::FromIterator;
+use std::rc::Rc;
 
 pub static BG_COLOR: Color = Color {
     array: [1.0, 1.0, 1.0, 1.0],
@@ -12,305 +22,4634 @@ struct Aabb<V: Vector> {
     min: V,
     max: V,
     center: V,
-    surface: Surface,
+    material: usize,
 }
 
-#[derive(Debug)]
-struct AabbRay<V: Vector> {
-    origin: V,
-    dir: V,
-    dir_inverse: V,
+impl<V: Vector> Aabb<V> {
+    fn from_cube(position: V, cube: &Cube, material: usize) -> Self {
+        let half_size = cube.size / 2.0;
+
+        Aabb {
+            min: V::from_iter(position.components().iter().map(|c| c - half_size)),
+            max: V::from_iter(position.components().iter().map(|c| c + half_size)),
+            center: position,
+            material,
+        }
+    }
 }
 
+// Spheres stored per property, so the intersection tests only read their geometry
 #[derive(Debug)]
-pub struct DimensionalWorld<V: Vector> {
-    center: V,
-    cam_pos: V,
-    lights: Vec<(V, Light)>,
-    spheres: Vec<(V, Sphere)>,
-    aabbs: Vec<Aabb<V>>,
+struct Spheres<V: Vector> {
+    centers: Vec<V>,
+    radii: Vec<Float>,
+    materials: Vec<usize>,
 }
 
-impl<V: Vector> DimensionalWorld<V> {
-    pub fn from_world(world: &World, cam_pos: V) -> Self {
-        Self {
-            center: V::new(),
-            cam_pos,
-            lights: world
-                .lights
-                .iter()
-                .map(|(position, s)| {
-                    let position = V::pad(&position, 0.0);
-                    (position, s.to_owned())
-                })
-                .collect(),
-            spheres: world
-                .spheres
-                .iter()
-                .map(|(position, s)| {
-                    let position = V::pad(&position, 0.0);
-                    (position, s.to_owned())
-                })
-                .collect(),
-            aabbs: world
-                .cubes
-                .iter()
-                .map(|(position, cube)| {
-                    let position = V::pad(&position, 0.0);
-                    let half_size = cube.size / 2.0;
-
-                    Aabb {
-                        min: V::from_iter(position.components().iter().map(|c| c - half_size)),
-                        max: V::from_iter(position.components().iter().map(|c| c + half_size)),
-                        center: position,
-                        surface: cube.surface.clone(),
-                    }
-                })
-                .collect(),
+impl<V: Vector> FromIterator<(V, Float, usize)> for Spheres<V> {
+    fn from_iter<I: IntoIterator<Item = (V, Float, usize)>>(iter: I) -> Self {
+        let mut spheres = Spheres {
+            centers: vec![],
+            radii: vec![],
+            materials: vec![],
+        };
+
+        for (center, radius, material) in iter {
+            spheres.centers.push(center);
+            spheres.radii.push(radius);
+            spheres.materials.push(material);
         }
+
+        spheres
     }
 }
 
-struct Intersection<V: Vector> {
-    position: V,
-    normal: V,
-    distance: Float,
-    surface: Surface,
+// Boxes stored per property like `Spheres`
+#[derive(Debug)]
+struct Aabbs<V: Vector> {
+    mins: Vec<V>,
+    maxs: Vec<V>,
+    centers: Vec<V>,
+    materials: Vec<usize>,
 }
 
-fn test_sphere_intersection<V: Vector>(
-    origin: &V,
-    ray: &V,
-    center: &V,
-    sphere: &Sphere,
-) -> Option<Intersection<V>> {
-    let origin_to_sphere = *center - *origin;
+impl<V: Vector> FromIterator<Aabb<V>> for Aabbs<V> {
+    fn from_iter<I: IntoIterator<Item = Aabb<V>>>(iter: I) -> Self {
+        let mut aabbs = Aabbs {
+            mins: vec![],
+            maxs: vec![],
+            centers: vec![],
+            materials: vec![],
+        };
 
-    // len of ray to the point where it's closest to the sphere center
-    let tc = ray.dot(&origin_to_sphere);
+        for aabb in iter {
+            aabbs.mins.push(aabb.min);
+            aabbs.maxs.push(aabb.max);
+            aabbs.centers.push(aabb.center);
+            aabbs.materials.push(aabb.material);
+        }
 
-    if tc > 0.0 {
-        let origin_to_sphere_len = origin_to_sphere.length();
+        aabbs
+    }
+}
 
-        // center of sphere to ray
-        let d = Float::sqrt(origin_to_sphere_len * origin_to_sphere_len - tc * tc);
+#[derive(Debug)]
+struct HyperCone<V: Vector> {
+    apex: V,
+    axis: V,
+    cos_squared: Float,
+    height: Float,
+    // Radius of the cap at the base, `None` if the cone is open
+    cap_radius: Option<Float>,
+    material: usize,
+}
 
-        // if we hit the sphere
-        if d < sphere.radius {
-            // length from intersection to the point where d hits the ray (i.e. end of tc)
-            let t1c = Float::sqrt(sphere.radius * sphere.radius - d * d);
+#[derive(Debug)]
+struct HyperCapsule<V: Vector> {
+    from: V,
+    to: V,
+    radius: Float,
+    material: usize,
+}
 
-            // length to first intersection
-            let tc1 = tc - t1c;
+#[derive(Debug)]
+struct SolidSimplex<V: Vector> {
+    origin: V,
+    // Gradients of the barycentric coordinates 1..=N, the coordinate 0 is implied
+    gradients: V::Matrix,
+    material: usize,
+}
 
-            // point of first intersection on the ray
-            let first_intersection = *ray * tc1;
-            let hit = *origin + first_intersection;
+#[derive(Debug)]
+struct Complex<V: Vector> {
+    position: V,
+    // Indices into `DimensionalWorld::vertices`, N per facet
+    facets: Vec<u32>,
+    bounds_center: V,
+    bounds_radius: Float,
+    material: usize,
+}
 
-            return Some(Intersection {
-                position: hit,
-                normal: (hit - *center).normalize(),
-                distance: (hit - *origin).length(),
-                surface: sphere.surface.clone(),
-            });
-        }
-    }
+#[derive(Debug)]
+struct ConvexPolytope<V: Vector> {
+    center: V,
+    // Unit normal and offset, inside is where `normal . (p - center) <= offset`
+    half_spaces: Vec<(V, Float)>,
+    // Only known for polytopes which are closed in every axis of this dimension
+    bounds_radius: Option<Float>,
+    material: usize,
+}
 
-    None
+#[derive(Debug)]
+struct HyperTorus<V: Vector> {
+    center: V,
+    kind: TorusKind,
+    radii: [Float; 3],
+    bounds_radius: Float,
+    material: usize,
 }
 
-fn axis_normalize<V: Vector>(v: &V) -> V {
-    let mut most_dominat: Float = 0.0;
+#[derive(Debug)]
+struct SdfObject<V: Vector> {
+    position: V,
+    tree: SdfTree<V>,
+    // Bounding sphere relative to `position`, `None` for infinite shapes
+    bounds: Option<(V, Float)>,
+    material: usize,
+}
 
-    for comp in v.components() {
-        let comp = *comp;
-        if comp.abs() > most_dominat.abs() {
-            most_dominat = comp
-        }
-    }
+#[derive(Debug)]
+struct MetaballObject<V: Vector> {
+    // Center, radius and strength of every ball
+    balls: Vec<(V, Float, Float)>,
+    threshold: Float,
+    // Upper bound of the slope of the field, turns it into a distance bound
+    lipschitz: Float,
+    material: usize,
+}
 
-    let result = v.components().iter().cloned().map(|comp| {
-        if comp == most_dominat {
-            most_dominat.signum()
-        } else {
-            0.0
-        }
-    });
+#[derive(Debug)]
+struct GraphObject<V: Vector> {
+    origin: V,
+    expression: Expr,
+    extent: Float,
+    // Axis of the function value, the others are the variables
+    height_axis: usize,
+    material: usize,
+}
 
-    V::from_iter(result)
+#[derive(Debug)]
+struct LatticeObject<V: Vector> {
+    origin: V,
+    // Maps from lattice coordinates to world space, i.e. the columns are the basis vectors
+    basis: V::Matrix,
+    inverse: V::Matrix,
+    // Motif spheres that might reach into a cell, relative to the lattice point at its
+    // corner. Center and index into `motif`.
+    candidates: Vec<(V, usize)>,
+    // Radius and material
+    motif: Vec<(Float, usize)>,
+    max_distance: Float,
 }
 
-fn test_aabb_intersection<V: Vector>(
-    ray: &AabbRay<V>,
-    aabb: &Aabb<V>,
-) -> (Option<Intersection<V>>, Option<Intersection<V>>) {
-    let mut clamped_min = -Float::INFINITY;
-    let mut clamped_max = Float::INFINITY;
+// Placement of a prototype, `p' = matrix * p + translation`
+#[derive(Debug)]
+struct InstanceObject<V: Vector> {
+    prototype: usize,
+    matrix: V::Matrix,
+    translation: V,
+    inverse: V::Matrix,
+    // Inverse transposed, keeps normals orthogonal to the surface
+    normal_matrix: V::Matrix,
+    // Replaces the materials of the prototype
+    material: Option<usize>,
+}
 
-    for (((origin_comp, ray_inverse), bounds_min), bounds_max) in ray
-        .origin
-        .components()
-        .into_iter()
-        .zip(ray.dir_inverse.components())
-        .zip(aabb.min.components())
-        .zip(aabb.max.components())
-    {
-        let tx1 = (bounds_min - origin_comp) * ray_inverse;
-        let tx2 = (bounds_max - origin_comp) * ray_inverse;
+// Index of an object in one of the lists of `DimensionalWorld`
+#[derive(Debug, Clone, Copy)]
+enum Object {
+    Sphere(usize),
+    Aabb(usize),
+    Cone(usize),
+    Capsule(usize),
+    Simplex(usize),
+    Complex(usize),
+    Polytope(usize),
+    Torus(usize),
+    Sdf(usize),
+    Csg(usize),
+    Metaballs(usize),
+    Graph(usize),
+    Lattice(usize),
+    Instance(usize),
+}
 
-        clamped_min = Float::max(clamped_min, Float::min(tx1, tx2));
-        clamped_max = Float::min(clamped_max, Float::max(tx1, tx2));
-    }
+// Finds the bounded objects a ray might hit, by their index in `bounded` of the world
+#[derive(Debug)]
+enum Accelerator<V: Vector> {
+    // All of them in order
+    BruteForce,
+    Bvh(Bvh<V, usize>),
+    Grid(UniformGrid<V>),
+}
 
-    // cube is in line of the ray, not necessarily in front through
-    let in_line = clamped_min < clamped_max;
-
-    let intersection_in = if in_line && clamped_min > 0.0 {
-        let hit_in = ray.origin + (ray.dir * clamped_min);
-        Some(Intersection {
-            position: hit_in,
-            normal: axis_normalize(&(hit_in - aabb.center)),
-            distance: (hit_in - ray.origin).length(),
-            surface: aabb.surface.clone(),
-        })
-    } else {
-        None
+#[derive(Debug)]
+struct AabbRay<V: Vector> {
+    origin: V,
+    dir: V,
+    dir_inverse: V,
+}
+
+fn build_accelerator<V: Vector>(
+    acceleration: Acceleration,
+    bounded: &[(Bounds<V>, Object)],
+) -> Accelerator<V> {
+    let indexed = || {
+        bounded
+            .iter()
+            .enumerate()
+            .map(|(index, (bounds, _))| (*bounds, index))
+            .collect()
     };
 
-    let intersection_out = if in_line && clamped_max > 0.0 {
-        // let hit_out = ray.origin + (ray.dir * clamped_max);
-        // Some(Intersection {
-        //     position: hit_out,
-        //     normal: axis_normalize(&(*center - hit_out)),
-        //     distance: (hit_out - *origin).length(),
-        //     surface: cube.surface.clone(),
-        // })
+    match acceleration {
+        Acceleration::BruteForce => Accelerator::BruteForce,
+        Acceleration::Bvh => Accelerator::Bvh(Bvh::build(indexed())),
+        Acceleration::Grid => Accelerator::Grid(UniformGrid::build(indexed())),
+    }
+}
 
-        None
-    } else {
-        None
-    };
+// Where the picture is taken from, looking towards `center`
+pub struct Camera<V: Vector> {
+    pub position: V,
+    pub center: V,
+}
 
-    (intersection_in, intersection_out)
+// Part of the picture with the objects its primary rays might hit
+pub struct View<V: Vector> {
+    camera: Camera<V>,
+    // Whether each of the bounded objects of the world is visible, `None` if nothing
+    // was culled. The accelerator of the world skips the others.
+    visible: Option<Vec<bool>>,
 }
 
-fn get_all_intersections<V: Vector>(
-    world: &DimensionalWorld<V>,
-    origin: &V,
-    ray: &V,
-) -> Vec<Intersection<V>> {
-    let mut all = Vec::with_capacity(8);
+impl<V: Vector> View<V> {
+    // Covers the rectangle between the corners `(min_x, min_y)` and `(max_x, max_y)` in
+    // relative image coordinates
+    pub fn new(
+        world: &DimensionalWorld<V>,
+        camera: Camera<V>,
+        (min_x, max_x): (Float, Float),
+        (min_y, max_y): (Float, Float),
+    ) -> Self {
+        let frustum = Frustum::new(
+            &camera,
+            [
+                primary_ray(&camera, min_x, min_y),
+                primary_ray(&camera, max_x, min_y),
+                primary_ray(&camera, max_x, max_y),
+                primary_ray(&camera, min_x, max_y),
+            ],
+        );
 
-    for (position, sphere) in &world.spheres {
-        if let Some(intersection) = test_sphere_intersection(origin, ray, &position, sphere) {
-            all.push(intersection)
+        let visible: Vec<bool> = world
+            .bounded
+            .iter()
+            .map(|(bounds, _)| frustum.might_contain(bounds))
+            .collect();
+
+        View {
+            camera,
+            visible: if visible.iter().all(|v| *v) {
+                None
+            } else {
+                Some(visible)
+            },
         }
     }
+}
 
-    let aabb_ray = AabbRay::<V> {
-        origin: origin.clone(),
-        dir: ray.clone(),
-        dir_inverse: V::pad(&[], 1.0) / *ray,
-    };
+// Whether the bounded object at `index` is looked at, see `View`
+fn is_visible(visible: Option<&[bool]>, index: usize) -> bool {
+    match visible {
+        Some(visible) => visible[index],
+        None => true,
+    }
+}
+
+// Pyramid from the camera through the four corner rays of a view. In N dimensions the
+// rays only span a 3D subspace, so anything away from it is outside as well.
+struct Frustum<V: Vector> {
+    apex: V,
+    // Orthonormal basis of the subspace of the rays
+    basis: Vec<V>,
+    // Unit normals of the sides pointing inwards, within the subspace
+    sides: Vec<V>,
+}
+
+impl<V: Vector> Frustum<V> {
+    fn new(camera: &Camera<V>, corners: [V; 4]) -> Self {
+        let sides = (0..4)
+            .filter_map(|i| {
+                // Spanned by neighbouring corners, the opposite corner lies inside
+                let side = orthonormalize(&[corners[i], corners[(i + 1) % 4]]);
+                let inside = orthogonal_part(&corners[(i + 2) % 4], &side);
+                let length = inside.length();
 
-    for aabb in &world.aabbs {
-        let (intersection_in, intersection_out) = test_aabb_intersection(&aabb_ray, &aabb);
+                // In 2D the rays only span a plane and two of the sides fall together
+                if length > ORTHOGONAL_EPSILON {
+                    Some(inside / length)
+                } else {
+                    None
+                }
+            })
+            .collect();
 
-        if let Some(intersection) = intersection_in {
-            all.push(intersection);
+        Frustum {
+            apex: camera.position,
+            basis: orthonormalize(&corners),
+            sides,
         }
-        // if let Some(intersection) = intersection_out {
-        //     all.push(intersection);
-        // }
     }
 
-    all.sort_by(|a, b| b.distance.partial_cmp(&a.distance).unwrap());
+    // Conservative, tests the sphere around the box
+    fn might_contain(&self, bounds: &Bounds<V>) -> bool {
+        let center = (bounds.min + bounds.max) / 2.0;
+        let radius = (bounds.max - bounds.min).length() / 2.0;
+        let offset = center - self.apex;
 
-    all
+        orthogonal_part(&offset, &self.basis).length() <= radius
+            && self
+                .sides
+                .iter()
+                .all(|normal| normal.dot(&offset) >= -radius)
+    }
 }
 
-fn get_light_color<V: Vector>(
-    mut light_color: Color,
-    shadow_casters: Vec<Intersection<V>>,
-) -> Color {
-    for sc in shadow_casters {
-        light_color.apply(&sc.surface.color.normalize());
-        light_color.adjust_brightness(1.0 - sc.surface.color.alpha())
+// Below this the rest of a unit vector counts as lying in the subspace
+const ORTHOGONAL_EPSILON: Float = 1e-4;
+
+// Gram-Schmidt on unit vectors, those in the span of the previous ones are left out
+fn orthonormalize<V: Vector>(vectors: &[V]) -> Vec<V> {
+    let mut basis = vec![];
+
+    for vector in vectors {
+        let rest = orthogonal_part(vector, &basis);
+        let length = rest.length();
+
+        if length > ORTHOGONAL_EPSILON {
+            basis.push(rest / length);
+        }
     }
 
-    light_color
+    basis
 }
 
-fn trace<V: Vector>(
-    world: &DimensionalWorld<V>,
-    cam_pos: &V,
-    ray: &V,
-    reflection_bounces: usize,
-) -> Color {
-    let all = get_all_intersections(world, &cam_pos, &ray);
-
-    let mut color = BG_COLOR;
-    for (index, hit) in all.iter().enumerate() {
-        if let Some(next) = all.get(index + 1) {
-            let next_alpha = next.surface.color.alpha();
-            if next_alpha == 1.0 {
-                // Hit is not shown anyways
-                continue;
+// Part of the vector orthogonal to the subspace with the orthonormal `basis`
+fn orthogonal_part<V: Vector>(vector: &V, basis: &[V]) -> V {
+    basis
+        .iter()
+        .fold(*vector, |rest, b| rest - *b * rest.dot(b))
+}
+
+#[derive(Debug)]
+pub struct DimensionalWorld<V: Vector> {
+    lights: Vec<(V, Light)>,
+    spheres: Spheres<V>,
+    aabbs: Aabbs<V>,
+    cones: Vec<HyperCone<V>>,
+    capsules: Vec<HyperCapsule<V>>,
+    simplices: Vec<SolidSimplex<V>>,
+    complexes: Vec<Complex<V>>,
+    polytopes: Vec<ConvexPolytope<V>>,
+    tori: Vec<HyperTorus<V>>,
+    sdfs: Vec<SdfObject<V>>,
+    csgs: Vec<CsgTree<V>>,
+    metaballs: Vec<MetaballObject<V>>,
+    graphs: Vec<GraphObject<V>>,
+    lattices: Vec<LatticeObject<V>>,
+    prototypes: Vec<DimensionalWorld<V>>,
+    instances: Vec<InstanceObject<V>>,
+    vertices: Vec<V>,
+    // Distinct surfaces of all objects, they refer to them by index. Prototypes use the
+    // table of the world they are placed in and leave theirs empty.
+    materials: Vec<Surface>,
+    accelerator: Accelerator<V>,
+    // Objects `accelerator` refers to by index
+    bounded: Vec<(Bounds<V>, Object)>,
+    // Objects without bounds, every ray is tested against them
+    unbounded: Vec<Object>,
+    // Box around everything in the world, `None` if something is unbounded
+    bounds: Option<Bounds<V>>,
+}
+
+impl<V: Vector + 'static> DimensionalWorld<V> {
+    // Built once per dimension and kept in the world until it changes
+    pub fn cached(world: &World) -> Rc<Self> {
+        let dimension = V::new().components().len();
+        let mut cache = world.dimensional.borrow_mut();
+
+        if let Some(cached) = cache.get(&dimension) {
+            if let Ok(cached) = cached.clone().downcast::<Self>() {
+                return cached;
             }
         }
 
-        let mut hit_color = hit.surface.color;
+        let dimensional = Rc::new(Self::from_world(world));
+        cache.insert(dimension, dimensional.clone());
 
-        // Ambient light color
-        let mut lights_color = Color::rgba(0.3, 0.3, 0.3, 1.0);
+        dimensional
+    }
+}
 
-        for (light_pos, light) in &world.lights {
-            let hit_to_light = (*light_pos - hit.position).normalize();
+impl<V: Vector> DimensionalWorld<V> {
+    pub fn from_world(world: &World) -> Self {
+        let mut materials = Materials::default();
+        let mut dimensional = Self::build(world, &mut materials);
+        dimensional.materials = materials.surfaces;
 
-            let shadow_casters = get_all_intersections(world, &hit.position, &hit_to_light);
-            let mut color = get_light_color(light.color, shadow_casters);
+        dimensional
+    }
 
-            let angle = hit.normal.dot(&hit_to_light);
-            // The more the brightness of the light is influenced by the angle the softer curves will look
-            let brightness = Float::max(angle * 0.8 + 0.2, 0.0);
-            color.adjust_brightness(brightness);
+    // Prototypes add their materials to the table of the world they are placed in
+    fn build(world: &World, materials: &mut Materials) -> Self {
+        let dimension = V::new().components().len();
+        let vertices: Vec<V> = world.vertices.iter().map(|v| V::pad(v, 0.0)).collect();
+        let acceleration = world.acceleration;
 
-            lights_color.combine(&color);
-        }
+        let mut world = Self {
+            lights: world
+                .lights
+                .iter()
+                .map(|(position, s)| {
+                    let position = V::pad(position, 0.0);
+                    (position, s.to_owned())
+                })
+                .collect(),
+            spheres: world
+                .spheres
+                .iter()
+                .map(|(position, sphere)| {
+                    let material = materials.add(&sphere.surface);
+                    (V::pad(position, 0.0), sphere.radius, material)
+                })
+                .collect(),
+            aabbs: world
+                .cubes
+                .iter()
+                .map(|(position, cube)| {
+                    let material = materials.add(&cube.surface);
+                    Aabb::from_cube(V::pad(position, 0.0), cube, material)
+                })
+                .collect(),
+            cones: world
+                .cones
+                .iter()
+                .filter_map(|(apex, cone)| {
+                    let axis = V::pad(&cone.axis, 0.0);
 
-        hit_color.apply(&lights_color);
+                    // The axis points into a dimension we are not rendering
+                    if axis.sum_of_squares() == 0.0 {
+                        return None;
+                    }
 
-        if reflection_bounces > 0 && hit.surface.reflection > 0.0 {
-            let ray_reflection = *ray - (hit.normal * 2.0 * ray.dot(&hit.normal));
-            let mut color = trace(
-                world,
-                &hit.position,
-                &ray_reflection,
-                reflection_bounces - 1,
-            );
-            color.set_alpha(hit.surface.reflection);
-            hit_color.mix(&color);
-        }
+                    let cos = Float::cos(cone.angle);
 
-        color.mix(&hit_color);
-    }
+                    Some(HyperCone {
+                        apex: V::pad(apex, 0.0),
+                        axis: axis / axis.length(),
+                        cos_squared: cos * cos,
+                        height: cone.height,
+                        cap_radius: if cone.capped {
+                            Some(cone.height * Float::tan(cone.angle))
+                        } else {
+                            None
+                        },
+                        material: materials.add(&cone.surface),
+                    })
+                })
+                .collect(),
+            capsules: world
+                .capsules
+                .iter()
+                .map(|(from, to, capsule)| HyperCapsule {
+                    from: V::pad(from, 0.0),
+                    to: V::pad(to, 0.0),
+                    radius: capsule.radius,
+                    material: materials.add(&capsule.surface),
+                })
+                .collect(),
+            simplices: world
+                .simplices
+                .iter()
+                .filter(|(_, simplex)| simplex.vertices.len() == dimension + 1)
+                .filter_map(|(position, simplex)| {
+                    let position = V::pad(position, 0.0);
+                    let origin = position + V::pad(&simplex.vertices[0], 0.0);
+                    let edges = simplex.vertices[1..]
+                        .iter()
+                        .map(|v| position + V::pad(v, 0.0) - origin);
 
-    color
-}
+                    Some(SolidSimplex {
+                        origin,
+                        // Degenerated simplices (i.e. flat ones) are not rendered
+                        gradients: V::Matrix::from_columns(edges).inverse()?,
+                        material: materials.add(&simplex.surface),
+                    })
+                })
+                .collect(),
+            complexes: world
+                .complexes
+                .iter()
+                .filter(|(_, complex)| complex.dimension == dimension)
+                .map(|(position, complex)| {
+                    let used = || complex.facets.iter().map(|i| vertices[*i as usize]);
 
-pub fn sample<V: Vector>(world: &DimensionalWorld<V>, rel_x: Float, rel_y: Float) -> Color {
-    let zoom = 1.4;
-    let cam_dir = (world.center - world.cam_pos).normalize();
-    let cam_dir_ort = V::pad(&[-cam_dir.components()[1], cam_dir.components()[0]], 0.0);
+                    let count = complex.facets.len().max(1) as Float;
+                    let local_center = used().fold(V::new(), |a, b| a + b) / count;
+                    let bounds_radius = used()
+                        .map(|v| (v - local_center).length())
+                        .fold(0.0, Float::max);
+                    let position = V::pad(position, 0.0);
 
-    let centered_x = rel_x * 2.0 - 1.0;
-    let centered_y = rel_y * 2.0 - 1.0;
-    let pos_on_sensor_x = cam_dir_ort * centered_x;
-    let pos_on_sensor_y = V::pad(&[0.0, 0.0, 1.0], 0.0) * centered_y;
-    let pos_on_sensor = pos_on_sensor_x + pos_on_sensor_y;
+                    Complex {
+                        position,
+                        facets: complex.facets.clone(),
+                        bounds_center: position + local_center,
+                        bounds_radius,
+                        material: materials.add(&complex.surface),
+                    }
+                })
+                .collect(),
+            polytopes: world
+                .polytopes
+                .iter()
+                .filter_map(|(position, polytope)| {
+                    let mut half_spaces = Vec::with_capacity(polytope.half_spaces.len());
+                    let mut polytope_dimension = 0;
+
+                    for (normal, offset) in &polytope.half_spaces {
+                        polytope_dimension = polytope_dimension.max(normal.len());
+                        let normal = V::pad(normal, 0.0);
+                        let length = normal.length();
+
+                        if length > 0.0 {
+                            half_spaces.push((normal / length, offset / length));
+                        } else if *offset < 0.0 {
+                            // Nothing in this dimension is inside the half-space
+                            return None;
+                        }
+                    }
+
+                    // Slices of a closed polytope stay inside of its bounds but the
+                    // axes it has no facets in are unbounded
+                    let dimension = V::new().components().len();
+                    let bounds_radius = polytope
+                        .bounds_radius
+                        .filter(|_| polytope_dimension >= dimension);
+
+                    Some(ConvexPolytope {
+                        center: V::pad(position, 0.0),
+                        half_spaces,
+                        bounds_radius,
+                        material: materials.add(&polytope.surface),
+                    })
+                })
+                .collect(),
+            tori: world
+                .tori
+                .iter()
+                .map(|(position, torus)| {
+                    let [major, middle, minor] = torus.radii;
+
+                    HyperTorus {
+                        center: V::pad(position, 0.0),
+                        kind: torus.kind,
+                        radii: torus.radii,
+                        bounds_radius: match torus.kind {
+                            TorusKind::Tiger => Float::sqrt(
+                                (major + minor) * (major + minor)
+                                    + (middle + minor) * (middle + minor),
+                            ),
+                            _ => major + middle + minor,
+                        },
+                        material: materials.add(&torus.surface),
+                    }
+                })
+                .collect(),
+            sdfs: world
+                .sdfs
+                .iter()
+                .map(|(position, sdf)| {
+                    let tree = SdfTree::from_world(&sdf.nodes);
 
-    let ray = (cam_dir * zoom + pos_on_sensor).normalize();
+                    SdfObject {
+                        position: V::pad(position, 0.0),
+                        bounds: tree.bounds(),
+                        tree,
+                        material: materials.add(&sdf.surface),
+                    }
+                })
+                .collect(),
+            csgs: world
+                .csgs
+                .iter()
+                .map(|(position, csg)| {
+                    let position = V::pad(position, 0.0);
 
-    let reflection_bounces = 2;
-    trace(world, &world.cam_pos, &ray, reflection_bounces)
+                    let nodes = csg
+                        .nodes
+                        .iter()
+                        .map(|node| match node {
+                            WorldCsgNode::Sphere(center, sphere) => CsgNode::Sphere(
+                                position + V::pad(center, 0.0),
+                                sphere.radius,
+                                materials.add(&sphere.surface),
+                            ),
+                            WorldCsgNode::Cube(center, cube) => CsgNode::Aabb(Aabb::from_cube(
+                                position + V::pad(center, 0.0),
+                                cube,
+                                materials.add(&cube.surface),
+                            )),
+                            WorldCsgNode::Union(a, b) => {
+                                CsgNode::Operation(CsgOperation::Union, *a as usize, *b as usize)
+                            }
+                            WorldCsgNode::Intersection(a, b) => CsgNode::Operation(
+                                CsgOperation::Intersection,
+                                *a as usize,
+                                *b as usize,
+                            ),
+                            WorldCsgNode::Difference(a, b) => CsgNode::Operation(
+                                CsgOperation::Difference,
+                                *a as usize,
+                                *b as usize,
+                            ),
+                        })
+                        .collect();
+
+                    CsgTree { nodes }
+                })
+                .collect(),
+            metaballs: world
+                .metaballs
+                .iter()
+                // Without a positive threshold everything outside of the balls would be inside
+                .filter(|(_, metaballs)| metaballs.threshold > 0.0)
+                .map(|(position, metaballs)| {
+                    let material = materials.add(&metaballs.surface);
+                    metaball_object(V::pad(position, 0.0), metaballs, material)
+                })
+                .collect(),
+            graphs: world
+                .graphs
+                .iter()
+                .map(|(origin, graph)| GraphObject {
+                    origin: V::pad(origin, 0.0),
+                    expression: graph.expression.clone(),
+                    extent: graph.extent,
+                    // z is up, in 2D there is only y
+                    height_axis: usize::min(2, dimension - 1),
+                    material: materials.add(&graph.surface),
+                })
+                .collect(),
+            lattices: world
+                .lattices
+                .iter()
+                .filter(|(_, lattice)| lattice.basis.len() == dimension)
+                .filter_map(|(origin, lattice)| {
+                    let basis =
+                        V::Matrix::from_columns(lattice.basis.iter().map(|v| V::pad(v, 0.0)));
+                    // Not a lattice if the basis vectors are linearly dependent
+                    let inverse = basis.inverse()?;
+
+                    Some(LatticeObject {
+                        origin: V::pad(origin, 0.0),
+                        basis,
+                        inverse,
+                        candidates: lattice_candidates(&basis, &inverse, &lattice.motif),
+                        motif: lattice
+                            .motif
+                            .iter()
+                            .map(|(_, sphere)| (sphere.radius, materials.add(&sphere.surface)))
+                            .collect(),
+                        max_distance: lattice.max_distance,
+                    })
+                })
+                .collect(),
+            prototypes: world
+                .prototypes
+                .iter()
+                .map(|prototype| DimensionalWorld::build(prototype, materials))
+                .collect(),
+            instances: world
+                .instances
+                .iter()
+                .filter(|instance| (instance.prototype as usize) < world.prototypes.len())
+                .filter_map(|instance| {
+                    let (matrix, translation) = affine_transform::<V>(&instance.transform);
+                    // Singular transformations flatten the prototype
+                    let inverse = matrix.inverse()?;
+
+                    Some(InstanceObject {
+                        prototype: instance.prototype as usize,
+                        matrix,
+                        translation,
+                        inverse,
+                        normal_matrix: inverse.transpose(),
+                        material: instance
+                            .surface
+                            .as_ref()
+                            .map(|surface| materials.add(surface)),
+                    })
+                })
+                .collect(),
+            vertices,
+            materials: vec![],
+            accelerator: Accelerator::BruteForce,
+            bounded: vec![],
+            unbounded: vec![],
+            bounds: None,
+        };
+
+        // Hits are found with rounding errors, some slack keeps them inside of the bounds
+        const BOUNDS_MARGIN: Float = 1e-2;
+
+        let mut bounded = vec![];
+        for object in world.objects() {
+            match world.object_bounds(object) {
+                Some(bounds) if bounds.is_empty() => {}
+                Some(bounds) => bounded.push((bounds.grow(BOUNDS_MARGIN), object)),
+                None => world.unbounded.push(object),
+            }
+        }
+
+        if world.unbounded.is_empty() {
+            world.bounds = Some(
+                bounded
+                    .iter()
+                    .fold(Bounds::empty(), |a, (bounds, _)| a.union(bounds)),
+            );
+        }
+
+        world.accelerator = build_accelerator(acceleration, &bounded);
+        world.bounded = bounded;
+
+        world
+    }
+
+    fn objects(&self) -> Vec<Object> {
+        let mut objects = vec![];
+
+        objects.extend((0..self.spheres.centers.len()).map(Object::Sphere));
+        objects.extend((0..self.aabbs.centers.len()).map(Object::Aabb));
+        objects.extend((0..self.cones.len()).map(Object::Cone));
+        objects.extend((0..self.capsules.len()).map(Object::Capsule));
+        objects.extend((0..self.simplices.len()).map(Object::Simplex));
+        objects.extend((0..self.complexes.len()).map(Object::Complex));
+        objects.extend((0..self.polytopes.len()).map(Object::Polytope));
+        objects.extend((0..self.tori.len()).map(Object::Torus));
+        objects.extend((0..self.sdfs.len()).map(Object::Sdf));
+        objects.extend((0..self.csgs.len()).map(Object::Csg));
+        objects.extend((0..self.metaballs.len()).map(Object::Metaballs));
+        objects.extend((0..self.graphs.len()).map(Object::Graph));
+        objects.extend((0..self.lattices.len()).map(Object::Lattice));
+        objects.extend((0..self.instances.len()).map(Object::Instance));
+
+        objects
+    }
+
+    // `None` if the object is infinite, or we do not know how far it reaches
+    fn object_bounds(&self, object: Object) -> Option<Bounds<V>> {
+        match object {
+            Object::Sphere(i) => Some(Bounds::sphere(
+                self.spheres.centers[i],
+                self.spheres.radii[i],
+            )),
+            Object::Aabb(i) => Some(Bounds {
+                min: self.aabbs.mins[i],
+                max: self.aabbs.maxs[i],
+            }),
+            Object::Cone(i) => {
+                let cone = &self.cones[i];
+                let base = cone.apex + cone.axis * cone.height;
+                let radius = cone.cap_radius.unwrap_or_else(|| {
+                    cone.height * Float::sqrt(1.0 - cone.cos_squared) / cone.cos_squared.sqrt()
+                });
+
+                // The base is a disc orthogonal to the axis
+                let extent = V::from_iter(
+                    cone.axis
+                        .components()
+                        .iter()
+                        .map(|a| radius * Float::sqrt(Float::max(1.0 - a * a, 0.0))),
+                );
+
+                Some(Bounds::point(cone.apex).union(&Bounds {
+                    min: base - extent,
+                    max: base + extent,
+                }))
+            }
+            Object::Capsule(i) => {
+                let capsule = &self.capsules[i];
+                let ends = Bounds::point(capsule.from).union(&Bounds::point(capsule.to));
+                Some(ends.grow(capsule.radius))
+            }
+            Object::Simplex(i) => {
+                let simplex = &self.simplices[i];
+                // The rows of the transposed inverse of the gradients are the edges
+                let edges = simplex.gradients.inverse()?.transpose();
+
+                Some(
+                    edges
+                        .as_ref()
+                        .iter()
+                        .fold(Bounds::point(simplex.origin), |bounds, edge| {
+                            bounds.union(&Bounds::point(simplex.origin + *edge))
+                        }),
+                )
+            }
+            Object::Complex(i) => {
+                let complex = &self.complexes[i];
+                Some(Bounds::sphere(complex.bounds_center, complex.bounds_radius))
+            }
+            Object::Torus(i) => {
+                let torus = &self.tori[i];
+                Some(Bounds::sphere(torus.center, torus.bounds_radius))
+            }
+            Object::Sdf(i) => {
+                let sdf = &self.sdfs[i];
+                let (center, radius) = sdf.bounds.as_ref()?;
+                Some(Bounds::sphere(sdf.position + *center, *radius))
+            }
+            Object::Csg(i) => {
+                // Only the union of all leafs is a safe bound
+                Some(
+                    self.csgs[i]
+                        .nodes
+                        .iter()
+                        .fold(Bounds::empty(), |bounds, node| match node {
+                            CsgNode::Sphere(center, radius, _) => {
+                                bounds.union(&Bounds::sphere(*center, *radius))
+                            }
+                            CsgNode::Aabb(aabb) => bounds.union(&Bounds {
+                                min: aabb.min,
+                                max: aabb.max,
+                            }),
+                            CsgNode::Operation(..) => bounds,
+                        }),
+                )
+            }
+            Object::Metaballs(i) => {
+                // The surface is where balls with a positive strength overlap
+                Some(
+                    self.metaballs[i]
+                        .balls
+                        .iter()
+                        .filter(|(_, _, strength)| *strength > 0.0)
+                        .fold(Bounds::empty(), |bounds, (center, radius, _)| {
+                            bounds.union(&Bounds::sphere(*center, *radius))
+                        }),
+                )
+            }
+            Object::Instance(i) => {
+                let instance = &self.instances[i];
+                let bounds = self.prototypes[instance.prototype].bounds?;
+
+                if bounds.is_empty() {
+                    Some(bounds)
+                } else {
+                    Some(bounds.transform(&instance.matrix, &instance.translation))
+                }
+            }
+            // Half-spaces, graphs and lattices might reach out infinitely
+            Object::Polytope(i) => {
+                let polytope = &self.polytopes[i];
+                Some(Bounds::sphere(polytope.center, polytope.bounds_radius?))
+            }
+            Object::Graph(_) | Object::Lattice(_) => None,
+        }
+    }
+}
+
+// Table of the distinct surfaces, objects with equal ones share an entry
+#[derive(Default)]
+struct Materials {
+    surfaces: Vec<Surface>,
+    // Bit patterns of the fields of every surface
+    indices: HashMap<Vec<u32>, usize>,
+}
+
+impl Materials {
+    fn add(&mut self, surface: &Surface) -> usize {
+        let mut key: Vec<u32> = surface
+            .color
+            .array
+            .iter()
+            .chain(&surface.specular.array)
+            .chain(&[surface.reflection, surface.shininess])
+            .map(|value| value.to_bits())
+            .collect();
+        // Without refraction the key is one shorter than with any
+        key.extend(surface.refraction.map(Float::to_bits));
+
+        let surfaces = &mut self.surfaces;
+        *self.indices.entry(key).or_insert_with(|| {
+            surfaces.push(surface.clone());
+            surfaces.len() - 1
+        })
+    }
+}
+
+fn metaball_object<V: Vector>(
+    position: V,
+    metaballs: &Metaballs,
+    material: usize,
+) -> MetaballObject<V> {
+    let balls: Vec<(V, Float, Float)> = metaballs
+        .balls
+        .iter()
+        .filter(|(_, radius, _)| *radius > 0.0)
+        .map(|(center, radius, strength)| (position + V::pad(center, 0.0), *radius, *strength))
+        .collect();
+
+    // The steepest slope of the falloff (1 - d^2/r^2)^3 is 96 / (25 sqrt(5) r)
+    let lipschitz = balls
+        .iter()
+        .map(|(_, radius, strength)| strength.abs() * 96.0 / (25.0 * Float::sqrt(5.0) * radius))
+        .sum();
+
+    MetaballObject {
+        balls,
+        threshold: metaballs.threshold,
+        lipschitz,
+        material,
+    }
+}
+
+// Finds all lattice points (relative to the corner of a cell) whose motif spheres
+// might reach into the cell, using their bounding box in lattice coordinates
+fn lattice_candidates<V: Vector>(
+    basis: &V::Matrix,
+    inverse: &V::Matrix,
+    motif: &[(Vec<Float>, Sphere)],
+) -> Vec<(V, usize)> {
+    let mut candidates = vec![];
+
+    for (index, (offset, sphere)) in motif.iter().enumerate() {
+        let offset = V::pad(offset, 0.0);
+        let lattice_offset = inverse.transform(&offset);
+
+        // Range of lattice points along each axis
+        let ranges: Vec<(Float, Float)> = inverse
+            .as_ref()
+            .iter()
+            .zip(lattice_offset.components())
+            .map(|(row, m)| {
+                let extent = sphere.radius * row.length();
+                (
+                    Float::floor(-m - extent) + 1.0,
+                    Float::ceil(1.0 - m + extent) - 1.0,
+                )
+            })
+            .collect();
+
+        // Walk through all combinations like an odometer
+        let mut point = V::from_iter(ranges.iter().map(|(from, _)| *from));
+        loop {
+            candidates.push((basis.transform(&point) + offset, index));
+
+            let mut axis = 0;
+            while axis < ranges.len() {
+                let component = &mut point.components_mut()[axis];
+                if *component < ranges[axis].1 {
+                    *component += 1.0;
+                    break;
+                }
+                *component = ranges[axis].0;
+                axis += 1;
+            }
+
+            if axis == ranges.len() {
+                break;
+            }
+        }
+    }
+
+    candidates
+}
+
+// Steps involving axes we don't render are skipped
+fn affine_transform<V: Vector>(transform: &Transform) -> (V::Matrix, V) {
+    let mut matrix = V::Matrix::identity();
+    let mut translation = V::new();
+    let dimension = translation.components().len();
+
+    for step in &transform.steps {
+        match step {
+            TransformStep::Translate(offset) => {
+                translation = translation + V::pad(offset, 0.0);
+            }
+            TransformStep::Scale(factor) => {
+                for row in matrix.as_mut() {
+                    *row = *row * *factor;
+                }
+                translation = translation * *factor;
+            }
+            TransformStep::ScaleAxis(axis, factor) if *axis < dimension => {
+                matrix.as_mut()[*axis] = matrix.as_ref()[*axis] * *factor;
+                translation.components_mut()[*axis] *= factor;
+            }
+            TransformStep::Rotate(a, b, angle) if *a < dimension && *b < dimension => {
+                let (sin, cos) = angle.sin_cos();
+
+                let (row_a, row_b) = (matrix.as_ref()[*a], matrix.as_ref()[*b]);
+                matrix.as_mut()[*a] = row_a * cos - row_b * sin;
+                matrix.as_mut()[*b] = row_a * sin + row_b * cos;
+
+                let (t_a, t_b) = (translation.components()[*a], translation.components()[*b]);
+                translation.components_mut()[*a] = t_a * cos - t_b * sin;
+                translation.components_mut()[*b] = t_a * sin + t_b * cos;
+            }
+            TransformStep::Shear(axis, by_axis, factor)
+                if *axis < dimension && *by_axis < dimension =>
+            {
+                matrix.as_mut()[*axis] =
+                    matrix.as_ref()[*axis] + matrix.as_ref()[*by_axis] * *factor;
+
+                let shift = translation.components()[*by_axis] * factor;
+                translation.components_mut()[*axis] += shift;
+            }
+            _ => {}
+        }
+    }
+
+    (matrix, translation)
+}
+
+struct Intersection<V: Vector> {
+    position: V,
+    // Always points out of the object, even if the ray leaves it
+    normal: V,
+    distance: Float,
+    // Index into `DimensionalWorld::materials`
+    material: usize,
+    // The ray hits the surface from the inside, i.e. it leaves the object
+    inside: bool,
+}
+
+// Receives the hits of the intersection routines
+trait Hits<V: Vector> {
+    fn push(&mut self, hit: Intersection<V>);
+
+    // Hits further away would not change anything, lets the search skip them. Zero once
+    // no hit matters anymore.
+    fn max_distance(&self) -> Float {
+        Float::INFINITY
+    }
+}
+
+// Nearest hits along a ray ordered by distance. Hits behind an opaque one can not be
+// seen and are dropped, if there are still too many the farthest ones are.
+struct HitBuffer<'a, V: Vector> {
+    materials: &'a [Surface],
+    hits: [Option<Intersection<V>>; MAX_HITS],
+    len: usize,
+    max_distance: Float,
+}
+
+const MAX_HITS: usize = 16;
+
+impl<'a, V: Vector> HitBuffer<'a, V> {
+    fn new(materials: &'a [Surface]) -> Self {
+        HitBuffer {
+            materials,
+            hits: Default::default(),
+            len: 0,
+            max_distance: Float::INFINITY,
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Intersection<V>> {
+        self.hits[..self.len].iter().flatten()
+    }
+}
+
+impl<'a, V: Vector> Hits<V> for HitBuffer<'a, V> {
+    fn push(&mut self, hit: Intersection<V>) {
+        if hit.distance >= self.max_distance {
+            return;
+        }
+
+        let mut index = self.len;
+        while index > 0 && self.hits[index - 1].as_ref().unwrap().distance > hit.distance {
+            index -= 1;
+        }
+
+        if index == MAX_HITS {
+            return;
+        }
+
+        if self.materials[hit.material].color.alpha() >= 1.0 {
+            self.max_distance = hit.distance;
+            self.len = index + 1;
+        } else {
+            self.len = usize::min(self.len + 1, MAX_HITS);
+        }
+
+        // Make room by moving the farther hits back
+        for i in (index + 1..self.len).rev() {
+            self.hits[i] = self.hits[i - 1].take();
+        }
+        self.hits[index] = Some(hit);
+    }
+
+    fn max_distance(&self) -> Float {
+        self.max_distance
+    }
+}
+
+// Items along a ray ordered by distance, on the stack so tracing a ray doesn't allocate.
+// Once it is full the farthest items are dropped, `dropped` counts them.
+struct SortedBuffer<T: Copy, const N: usize> {
+    items: [Option<(Float, T)>; N],
+    len: usize,
+    dropped: usize,
+}
+
+impl<T: Copy, const N: usize> SortedBuffer<T, N> {
+    fn new() -> Self {
+        SortedBuffer {
+            items: [None; N],
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    fn insert(&mut self, t: Float, item: T) {
+        let mut index = self.len;
+        while index > 0 && self.items[index - 1].unwrap().0 > t {
+            index -= 1;
+        }
+
+        if self.len == N {
+            self.dropped += 1;
+            if index == N {
+                return;
+            }
+        } else {
+            self.len += 1;
+        }
+
+        for i in (index + 1..self.len).rev() {
+            self.items[i] = self.items[i - 1];
+        }
+        self.items[index] = Some((t, item));
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Float, T)> + Clone + '_ {
+        self.items[..self.len].iter().flatten().copied()
+    }
+}
+
+// Passes on the hits inside of one grid cell, objects reaching into several cells are
+// tested in each of them
+struct CellHits<'a, V: Vector, H: Hits<V> + 'a> {
+    hits: &'a mut H,
+    origin: V,
+    ray: V,
+    t_enter: Float,
+    t_exit: Float,
+}
+
+impl<'a, V: Vector, H: Hits<V>> Hits<V> for CellHits<'a, V, H> {
+    fn push(&mut self, hit: Intersection<V>) {
+        let t = (hit.position - self.origin).dot(&self.ray) / self.ray.sum_of_squares();
+
+        if t >= self.t_enter && t < self.t_exit {
+            self.hits.push(hit);
+        }
+    }
+
+    fn max_distance(&self) -> Float {
+        self.hits.max_distance()
+    }
+}
+
+// Moves the hits of a prototype into the space of its instance. Dynamic, otherwise
+// instances in prototypes would nest these types endlessly.
+struct InstanceHits<'a, V: Vector + 'a> {
+    instance: &'a InstanceObject<V>,
+    origin: V,
+    hits: &'a mut dyn Hits<V>,
+}
+
+impl<'a, V: Vector> Hits<V> for InstanceHits<'a, V> {
+    fn push(&mut self, hit: Intersection<V>) {
+        let instance = self.instance;
+        let position = instance.matrix.transform(&hit.position) + instance.translation;
+        let normal = instance.normal_matrix.transform(&hit.normal);
+
+        self.hits.push(Intersection {
+            position,
+            normal: normal.normalize(),
+            distance: (position - self.origin).length(),
+            material: instance.material.unwrap_or(hit.material),
+            inside: hit.inside,
+        });
+    }
+
+    // Distances in the space of the prototype are not the same as in the world
+    fn max_distance(&self) -> Float {
+        if self.hits.max_distance() > 0.0 {
+            Float::INFINITY
+        } else {
+            0.0
+        }
+    }
+}
+
+// Light that makes it through all objects along a shadow ray
+struct Transmittance<'a> {
+    color: Color,
+    materials: &'a [Surface],
+}
+
+impl<'a, V: Vector> Hits<V> for Transmittance<'a> {
+    fn push(&mut self, hit: Intersection<V>) {
+        // Every object dims the light once, where the ray enters it
+        if hit.inside {
+            return;
+        }
+
+        let color = self.materials[hit.material].color;
+        self.color.apply(&color.normalize());
+        self.color.adjust_brightness(1.0 - color.alpha());
+    }
+
+    // Once nothing gets through anymore
+    fn max_distance(&self) -> Float {
+        let color = self.color;
+        if color.red() <= 0.0 && color.green() <= 0.0 && color.blue() <= 0.0 {
+            0.0
+        } else {
+            Float::INFINITY
+        }
+    }
+}
+
+// Point where a ray enters or exits an object, the normal always points outwards
+#[derive(Debug, Clone, Copy)]
+struct SpanHit<V: Vector> {
+    t: Float,
+    normal: V,
+    material: usize,
+}
+
+impl<V: Vector> SpanHit<V> {
+    fn to_intersection(self, origin: &V, ray: &V, inside: bool) -> Intersection<V> {
+        let position = *origin + *ray * self.t;
+
+        Intersection {
+            position,
+            normal: self.normal,
+            distance: (position - *origin).length(),
+            material: self.material,
+            inside,
+        }
+    }
+}
+
+// Part of a ray that is inside of an object, might start (or end) behind the origin
+#[derive(Debug, Clone, Copy)]
+struct Span<V: Vector> {
+    enter: SpanHit<V>,
+    exit: SpanHit<V>,
+}
+
+impl<V: Vector> Span<V> {
+    // Adds the entry and the exit if they are in front of the origin
+    fn push_hits<H: Hits<V>>(&self, origin: &V, ray: &V, hits: &mut H) {
+        if self.enter.t > 0.0 {
+            hits.push(self.enter.to_intersection(origin, ray, false));
+        }
+        if self.exit.t > 0.0 {
+            hits.push(self.exit.to_intersection(origin, ray, true));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+#[derive(Debug)]
+enum CsgNode<V: Vector> {
+    Sphere(V, Float, usize),
+    Aabb(Aabb<V>),
+    Operation(CsgOperation, usize, usize),
+}
+
+// The last node is the root
+#[derive(Debug)]
+struct CsgTree<V: Vector> {
+    nodes: Vec<CsgNode<V>>,
+}
+
+fn test_sphere_intersection<V: Vector, H: Hits<V>>(
+    origin: &V,
+    ray: &V,
+    spheres: &Spheres<V>,
+    index: usize,
+    hits: &mut H,
+) {
+    let center = &spheres.centers[index];
+    let radius = spheres.radii[index];
+
+    if let Some(span) = sphere_span(origin, ray, center, radius, spheres.materials[index]) {
+        span.push_hits(origin, ray, hits);
+    }
+}
+
+fn cone_normal<V: Vector>(cone: &HyperCone<V>, hit: &V) -> V {
+    let apex_to_hit = *hit - cone.apex;
+    let normal = apex_to_hit * cone.cos_squared - cone.axis * apex_to_hit.dot(&cone.axis);
+
+    if normal.sum_of_squares() > 0.0 {
+        normal / normal.length()
+    } else {
+        // The apex itself, there is no proper normal so we point away from the cone
+        cone.axis * -1.0
+    }
+}
+
+fn test_cone_intersection<V: Vector, H: Hits<V>>(
+    origin: &V,
+    ray: &V,
+    cone: &HyperCone<V>,
+    hits: &mut H,
+) {
+    const EPSILON: Float = 1e-6;
+
+    let apex_to_origin = *origin - cone.apex;
+
+    // Height along the axis of the origin and how fast the ray moves along the axis
+    let origin_height = apex_to_origin.dot(&cone.axis);
+    let ray_height = ray.dot(&cone.axis);
+
+    let in_height = |t: Float| {
+        let height = origin_height + ray_height * t;
+        t > 0.0 && height >= 0.0 && height <= cone.height
+    };
+
+    // A point `p` is on the (double) cone if `((p - apex) . axis)^2 = cos^2 * |p - apex|^2`,
+    // plugging in the ray gives us the quadratic `a * t^2 + b * t + c = 0`
+    let a = ray_height * ray_height - cone.cos_squared * ray.sum_of_squares();
+    let b = 2.0 * (ray_height * origin_height - cone.cos_squared * ray.dot(&apex_to_origin));
+    let c = origin_height * origin_height - cone.cos_squared * apex_to_origin.sum_of_squares();
+
+    let mut push_hit = |t: Float, normal: Option<V>| {
+        let position = *origin + *ray * t;
+        let normal = normal.unwrap_or_else(|| cone_normal(cone, &position));
+
+        hits.push(Intersection {
+            position,
+            normal,
+            distance: (position - *origin).length(),
+            material: cone.material,
+            inside: normal.dot(ray) > 0.0,
+        });
+    };
+
+    if a.abs() > EPSILON {
+        let discriminant = b * b - 4.0 * a * c;
+
+        if discriminant >= 0.0 {
+            let root = Float::sqrt(discriminant);
+            for t in [(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)] {
+                if in_height(t) {
+                    push_hit(t, None);
+                }
+            }
+        }
+    } else if b.abs() > EPSILON {
+        // The ray is parallel to the surface, so it only crosses it once
+        let t = -c / b;
+        if in_height(t) {
+            push_hit(t, None);
+        }
+    } else if c.abs() <= EPSILON {
+        // The ray runs along the surface, we hit it where it enters the height range
+        let bounds = if ray_height.abs() > EPSILON {
+            let t1 = -origin_height / ray_height;
+            let t2 = (cone.height - origin_height) / ray_height;
+            Some((Float::min(t1, t2), Float::max(t1, t2)))
+        } else if origin_height >= 0.0 && origin_height <= cone.height {
+            Some((0.0, Float::INFINITY))
+        } else {
+            None
+        };
+
+        if let Some((t_min, t_max)) = bounds {
+            if t_max > 0.0 {
+                push_hit(Float::max(t_min, EPSILON), None);
+            }
+        }
+    }
+
+    if let Some(cap_radius) = cone.cap_radius {
+        if ray_height.abs() > EPSILON {
+            let t = (cone.height - origin_height) / ray_height;
+            let cap_center = cone.apex + cone.axis * cone.height;
+
+            if t > 0.0
+                && (*origin + *ray * t - cap_center).sum_of_squares() <= cap_radius * cap_radius
+            {
+                push_hit(t, Some(cone.axis));
+            }
+        }
+    }
+}
+
+fn test_capsule_intersection<V: Vector, H: Hits<V>>(
+    origin: &V,
+    ray: &V,
+    capsule: &HyperCapsule<V>,
+    hits: &mut H,
+) {
+    let axis = capsule.to - capsule.from;
+    let from_to_origin = *origin - capsule.from;
+    let radius_squared = capsule.radius * capsule.radius;
+
+    let axis_squared = axis.sum_of_squares();
+    let axis_ray = axis.dot(ray);
+    let axis_origin = axis.dot(&from_to_origin);
+
+    // The infinite cylinder around the axis, scaled by `axis_squared` to avoid divisions
+    let a = axis_squared - axis_ray * axis_ray;
+    let b = axis_squared * ray.dot(&from_to_origin) - axis_origin * axis_ray;
+    let c = axis_squared * from_to_origin.sum_of_squares()
+        - axis_origin * axis_origin
+        - radius_squared * axis_squared;
+    let discriminant = b * b - a * c;
+
+    let hit = |t: Float, center: V| SpanHit {
+        t,
+        normal: (*origin + *ray * t - center) / capsule.radius,
+        material: capsule.material,
+    };
+
+    // The capsule is the convex union of the cylinder and the spheres at the ends,
+    // so we are inside of it from the first entry to the last exit of them
+    let mut enter: Option<SpanHit<V>> = None;
+    let mut exit: Option<SpanHit<V>> = None;
+
+    let mut extend = |candidate: SpanHit<V>, entering: bool| {
+        let current = if entering { &mut enter } else { &mut exit };
+        let better = match current {
+            None => true,
+            Some(current) if entering => candidate.t < current.t,
+            Some(current) => candidate.t > current.t,
+        };
+
+        if better {
+            *current = Some(candidate);
+        }
+    };
+
+    if a > 0.0 && discriminant >= 0.0 {
+        for (t, entering) in [
+            ((-b - discriminant.sqrt()) / a, true),
+            ((-b + discriminant.sqrt()) / a, false),
+        ] {
+            // Position of the hit along the axis, scaled by `axis_squared`
+            let y = axis_origin + t * axis_ray;
+
+            if y > 0.0 && y < axis_squared {
+                extend(hit(t, capsule.from + axis * (y / axis_squared)), entering);
+            }
+        }
+    }
+
+    for cap in &[capsule.from, capsule.to] {
+        if let Some((t_enter, t_exit)) = sphere_interval(origin, ray, cap, capsule.radius) {
+            extend(hit(t_enter, *cap), true);
+            extend(hit(t_exit, *cap), false);
+        }
+    }
+
+    if let (Some(enter), Some(exit)) = (enter, exit) {
+        Span { enter, exit }.push_hits(origin, ray, hits);
+    }
+}
+
+// Range of a ray inside of a convex object made up of half-spaces, along with the
+// outward normals of the facets where it enters and exits
+struct ClippedRay<V: Vector> {
+    t_min: Float,
+    t_max: Float,
+    enter_normal: Option<V>,
+    exit_normal: Option<V>,
+}
+
+impl<V: Vector> ClippedRay<V> {
+    fn new() -> Self {
+        Self {
+            t_min: -Float::INFINITY,
+            t_max: Float::INFINITY,
+            enter_normal: None,
+            exit_normal: None,
+        }
+    }
+
+    // Clips the range to the half-space where `base + rate * t >= 0`, `normal`
+    // points out of it. Returns `false` if nothing is left of the range.
+    fn clip(&mut self, base: Float, rate: Float, normal: V) -> bool {
+        if rate > 0.0 {
+            let t = -base / rate;
+            if t > self.t_min {
+                self.t_min = t;
+                self.enter_normal = Some(normal);
+            }
+        } else if rate < 0.0 {
+            let t = -base / rate;
+            if t < self.t_max {
+                self.t_max = t;
+                self.exit_normal = Some(normal);
+            }
+        } else if base < 0.0 {
+            return false;
+        }
+
+        self.t_min < self.t_max
+    }
+
+    // Unbounded objects might have no entry or exit
+    fn push_hits<H: Hits<V>>(&self, origin: &V, ray: &V, material: usize, hits: &mut H) {
+        for (t, normal, inside) in [
+            (self.t_min, self.enter_normal, false),
+            (self.t_max, self.exit_normal, true),
+        ] {
+            if let Some(normal) = normal {
+                if t > 0.0 {
+                    let hit = SpanHit {
+                        t,
+                        normal: normal.normalize(),
+                        material,
+                    };
+                    hits.push(hit.to_intersection(origin, ray, inside));
+                }
+            }
+        }
+    }
+}
+
+fn test_simplex_intersection<V: Vector, H: Hits<V>>(
+    origin: &V,
+    ray: &V,
+    simplex: &SolidSimplex<V>,
+    hits: &mut H,
+) {
+    let simplex_to_origin = *origin - simplex.origin;
+    let mut clipped = ClippedRay::new();
+
+    // The barycentric coordinate 0 is `1 - sum(others)`
+    let mut base_0 = 1.0;
+    let mut rate_0 = 0.0;
+    let mut gradient_0 = V::new();
+
+    for gradient in simplex.gradients.as_ref() {
+        let base = gradient.dot(&simplex_to_origin);
+        let rate = gradient.dot(ray);
+        base_0 -= base;
+        rate_0 -= rate;
+        gradient_0 = gradient_0 - *gradient;
+
+        if !clipped.clip(base, rate, *gradient * -1.0) {
+            return;
+        }
+    }
+
+    if !clipped.clip(base_0, rate_0, gradient_0 * -1.0) {
+        return;
+    }
+
+    clipped.push_hits(origin, ray, simplex.material, hits);
+}
+
+fn test_polytope_intersection<V: Vector, H: Hits<V>>(
+    origin: &V,
+    ray: &V,
+    polytope: &ConvexPolytope<V>,
+    hits: &mut H,
+) {
+    let center_to_origin = *origin - polytope.center;
+    let mut clipped = ClippedRay::new();
+
+    // Like the slab test for AABBs but with arbitrary normals
+    for (half_space_normal, offset) in &polytope.half_spaces {
+        let base = offset - half_space_normal.dot(&center_to_origin);
+        let rate = -half_space_normal.dot(ray);
+
+        if !clipped.clip(base, rate, *half_space_normal) {
+            return;
+        }
+    }
+
+    clipped.push_hits(origin, ray, polytope.material, hits);
+}
+
+const BARYCENTRIC_EPSILON: Float = 1e-5;
+const CROSSING_EPSILON: Float = 1e-4;
+const MAX_CROSSINGS: usize = 32;
+
+fn test_complex_intersection<V: Vector, H: Hits<V>>(
+    origin: &V,
+    ray: &V,
+    vertices: &[V],
+    complex: &Complex<V>,
+    hits: &mut H,
+) {
+    // Early out using the bounding sphere
+    let origin_to_center = complex.bounds_center - *origin;
+    let tc = ray.dot(&origin_to_center);
+    let d_squared = origin_to_center.sum_of_squares() - tc * tc;
+    if d_squared > complex.bounds_radius * complex.bounds_radius {
+        return;
+    }
+
+    let local_origin = *origin - complex.position;
+    let dimension = V::new().components().len();
+
+    // Distance and the normal facing the ray of every facet we pass through
+    let mut crossings: SortedBuffer<V, MAX_CROSSINGS> = SortedBuffer::new();
+
+    for facet in complex.facets.chunks(dimension) {
+        let v0 = vertices[facet[0] as usize];
+
+        // Solve `origin + t * ray = v0 + sum(l_i * (v_i - v0))` for `t` and the
+        // barycentric coordinates `l_i`
+        let columns = std::iter::once(*ray).chain(
+            facet[1..]
+                .iter()
+                .map(|index| v0 - vertices[*index as usize]),
+        );
+        let inverse = match V::Matrix::from_columns(columns).inverse() {
+            Some(inverse) => inverse,
+            // Ray is parallel to the facet
+            None => continue,
+        };
+        let solution = inverse.transform(&(v0 - local_origin));
+        let solution = solution.components();
+
+        let t = solution[0];
+        if t <= 0.0 {
+            continue;
+        }
+
+        // Rays through a ridge might just miss all facets sharing it due to rounding, the
+        // tolerance lets them hit several instead
+        let barycentric = &solution[1..];
+        if barycentric.iter().all(|l| *l >= -BARYCENTRIC_EPSILON)
+            && barycentric.iter().sum::<Float>() <= 1.0 + BARYCENTRIC_EPSILON
+        {
+            // The first row maps to `t` so it is orthogonal to the facet and
+            // points along the ray
+            crossings.insert(t, (inverse.as_ref()[0] * -1.0).normalize());
+        }
+    }
+
+    // Through a ridge shared by neighbouring facets the ray crosses all of them at once,
+    // but the boundary only once
+    let mut previous = Float::NEG_INFINITY;
+    let boundary = crossings.iter().filter(move |(t, _)| {
+        let next = t - previous > CROSSING_EPSILON * t;
+        previous = *t;
+        next
+    });
+
+    // The facets don't have a consistent orientation, but the boundary is closed
+    // so entries and exits alternate and we end up outside after the last one, even
+    // if that is one of the dropped ones
+    let count = boundary.clone().count() + crossings.dropped;
+    let starts_inside = count % 2 == 1;
+
+    for (index, (t, facing)) in boundary.enumerate() {
+        let inside = (index % 2 == 0) == starts_inside;
+        let hit = SpanHit {
+            t,
+            normal: if inside { facing * -1.0 } else { facing },
+            material: complex.material,
+        };
+
+        hits.push(hit.to_intersection(origin, ray, inside));
+    }
+}
+
+// Range of the ray inside of the sphere, might start behind the origin
+fn sphere_interval<V: Vector>(
+    origin: &V,
+    ray: &V,
+    center: &V,
+    radius: Float,
+) -> Option<(Float, Float)> {
+    // Rays are only about unit length, taking it into account keeps far hits on the surface
+    let length = ray.length();
+    let length_squared = length * length;
+
+    let origin_to_center = *center - *origin;
+    let tc = ray.dot(&origin_to_center) / length_squared;
+    let d_squared = origin_to_center.sum_of_squares() - tc * tc * length_squared;
+
+    if d_squared > radius * radius {
+        return None;
+    }
+
+    let t1c = Float::sqrt((radius * radius - d_squared) / length_squared);
+    Some((tc - t1c, tc + t1c))
+}
+
+// Finds the first root of a distance function along the ray within `t_min..t_max`.
+// The distance has to be a lower bound of the actual distance to the surface,
+// then stepping by it never skips over the surface. If we end up inside anyway
+// (e.g. because of rounding) we narrow down the crossing with a bisection.
+fn sphere_trace<V: Vector, F: Fn(&V) -> Float>(
+    origin: &V,
+    ray: &V,
+    t_min: Float,
+    t_max: Float,
+    distance: F,
+) -> Option<Float> {
+    const MAX_STEPS: usize = 256;
+    const HIT_DISTANCE: Float = 1e-4;
+    const MIN_STEP: Float = 1e-4;
+
+    let mut t = Float::max(t_min, 0.0);
+    let mut previous_t = t;
+
+    // Rays starting on (or inside) the surface, like shadow rays, first have to leave it
+    let mut leaving = distance(&(*origin + *ray * t)) < HIT_DISTANCE;
+
+    for _ in 0..MAX_STEPS {
+        if t > t_max {
+            return None;
+        }
+
+        let d = distance(&(*origin + *ray * t));
+
+        if leaving {
+            leaving = d < HIT_DISTANCE;
+            t += Float::max(d.abs(), MIN_STEP);
+            continue;
+        }
+
+        if d < 0.0 {
+            return Some(bisect(origin, ray, previous_t, t, &distance));
+        }
+        if d < HIT_DISTANCE {
+            return Some(t);
+        }
+
+        previous_t = t;
+        t += Float::max(d, MIN_STEP);
+    }
+
+    None
+}
+
+// Narrows down a sign change of `f` between `t_outside` and `t_inside`
+fn bisect<V: Vector, F: Fn(&V) -> Float>(
+    origin: &V,
+    ray: &V,
+    mut t_outside: Float,
+    mut t_inside: Float,
+    f: &F,
+) -> Float {
+    const STEPS: usize = 24;
+
+    for _ in 0..STEPS {
+        let t = (t_outside + t_inside) / 2.0;
+
+        if f(&(*origin + *ray * t)) < 0.0 {
+            t_inside = t;
+        } else {
+            t_outside = t;
+        }
+    }
+
+    t_outside
+}
+
+// Normal from the gradient of an implicit function using central differences
+fn gradient_normal<V: Vector, F: Fn(&V) -> Float>(point: &V, f: F) -> V {
+    const H: Float = 1e-3;
+
+    let mut gradient = V::new();
+
+    for axis in 0..gradient.components().len() {
+        let mut offset = V::new();
+        offset.components_mut()[axis] = H;
+
+        gradient.components_mut()[axis] = f(&(*point + offset)) - f(&(*point - offset));
+    }
+
+    gradient.normalize()
+}
+
+// Adds where the ray enters and leaves the first part of an implicit surface within
+// `t_min..t_max`. Rays starting inside of it only get the exit. False if there is none.
+fn push_implicit_hits<V: Vector, H: Hits<V>, F: Fn(&V) -> Float>(
+    origin: &V,
+    ray: &V,
+    t_min: Float,
+    t_max: Float,
+    distance: F,
+    material: usize,
+    hits: &mut H,
+) -> bool {
+    // Bounds are often tight, starting right on the surface would skip over it
+    const MARGIN: Float = 1e-2;
+
+    let t_start = Float::max(t_min - MARGIN, 0.0);
+    // Inside of the object the negated distance tells how far it is to the exit
+    let inside_distance = |p: &V| -distance(p);
+
+    let t_enter = if distance(&(*origin + *ray * t_start)) < 0.0 {
+        None
+    } else {
+        match sphere_trace(origin, ray, t_start, t_max, &distance) {
+            Some(t) => Some(t),
+            None => return false,
+        }
+    };
+
+    let t_exit = sphere_trace(
+        origin,
+        ray,
+        t_enter.unwrap_or(t_start),
+        t_max,
+        inside_distance,
+    );
+
+    for (t, inside) in [(t_enter, false), (t_exit, true)] {
+        if let Some(t) = t {
+            let hit = SpanHit {
+                t,
+                normal: gradient_normal(&(*origin + *ray * t), &distance),
+                material,
+            };
+            hits.push(hit.to_intersection(origin, ray, inside));
+        }
+    }
+
+    t_enter.is_some() || t_exit.is_some()
+}
+
+fn length_of(components: &[Float], from: usize, to: usize) -> Float {
+    let to = to.min(components.len());
+    let from = from.min(to);
+
+    components[from..to]
+        .iter()
+        .map(|c| c * c)
+        .sum::<Float>()
+        .sqrt()
+}
+
+// Exact distance in 4D, in lower dimensions we get a slice of the torus and in
+// higher dimensions the remaining components are treated like the last one
+fn torus_distance<V: Vector>(torus: &HyperTorus<V>, point: &V) -> Float {
+    let local = *point - torus.center;
+    let c = local.components();
+    let [major, middle, minor] = torus.radii;
+
+    let tube = match torus.kind {
+        TorusKind::Spheritorus => {
+            let ring = length_of(c, 0, 2) - major;
+            let rest = length_of(c, 2, c.len());
+            Float::sqrt(ring * ring + rest * rest)
+        }
+        TorusKind::Torisphere => {
+            let shell = length_of(c, 0, 3) - major;
+            let rest = length_of(c, 3, c.len());
+            Float::sqrt(shell * shell + rest * rest)
+        }
+        TorusKind::Tiger => {
+            let ring_xy = length_of(c, 0, 2) - major;
+            let ring_zw = length_of(c, 2, 4) - middle;
+            let rest = length_of(c, 4, c.len());
+            Float::sqrt(ring_xy * ring_xy + ring_zw * ring_zw + rest * rest)
+        }
+        TorusKind::Ditorus => {
+            let ring = length_of(c, 0, 2) - major;
+            let z = length_of(c, 2, 3);
+            let torus = Float::sqrt(ring * ring + z * z) - middle;
+            let rest = length_of(c, 3, c.len());
+            Float::sqrt(torus * torus + rest * rest)
+        }
+    };
+
+    tube - minor
+}
+
+fn test_torus_intersection<V: Vector, H: Hits<V>>(
+    origin: &V,
+    ray: &V,
+    torus: &HyperTorus<V>,
+    hits: &mut H,
+) {
+    if let Some((t_min, t_max)) = sphere_interval(origin, ray, &torus.center, torus.bounds_radius) {
+        let distance = |p: &V| torus_distance(torus, p);
+        push_implicit_hits(origin, ray, t_min, t_max, distance, torus.material, hits);
+    }
+}
+
+fn test_sdf_intersection<V: Vector, H: Hits<V>>(
+    origin: &V,
+    ray: &V,
+    sdf: &SdfObject<V>,
+    hits: &mut H,
+) {
+    // Infinite shapes are traced up to this distance
+    const MAX_DISTANCE: Float = 100.0;
+
+    let (t_min, t_max) = match &sdf.bounds {
+        Some((center, radius)) => {
+            match sphere_interval(origin, ray, &(sdf.position + *center), *radius) {
+                Some(interval) => interval,
+                None => return,
+            }
+        }
+        None => (0.0, MAX_DISTANCE),
+    };
+    let distance = |p: &V| sdf.tree.distance(&(*p - sdf.position));
+
+    push_implicit_hits(origin, ray, t_min, t_max, distance, sdf.material, hits);
+}
+
+// Sum of the smooth falloffs of all balls, zero outside of their radius
+fn metaball_field<V: Vector>(metaballs: &MetaballObject<V>, point: &V) -> Float {
+    metaballs
+        .balls
+        .iter()
+        .map(|(center, radius, strength)| {
+            let s = (*point - *center).sum_of_squares() / (radius * radius);
+            if s < 1.0 {
+                let falloff = 1.0 - s;
+                strength * falloff * falloff * falloff
+            } else {
+                0.0
+            }
+        })
+        .sum()
+}
+
+const MAX_INTERVALS: usize = 32;
+
+fn test_metaball_intersection<V: Vector, H: Hits<V>>(
+    origin: &V,
+    ray: &V,
+    metaballs: &MetaballObject<V>,
+    hits: &mut H,
+) {
+    // Only balls with a positive strength can lift the field up to the threshold
+    let mut intervals: SortedBuffer<Float, MAX_INTERVALS> = SortedBuffer::new();
+    let mut t_last = Float::NEG_INFINITY;
+    for (center, radius, _) in metaballs.balls.iter().filter(|(_, _, s)| *s > 0.0) {
+        if let Some((t_min, t_max)) = sphere_interval(origin, ray, center, *radius) {
+            if t_max > 0.0 {
+                intervals.insert(t_min, t_max);
+                t_last = Float::max(t_last, t_max);
+            }
+        }
+    }
+
+    let distance =
+        |p: &V| (metaballs.threshold - metaball_field(metaballs, p)) / metaballs.lipschitz;
+
+    // Between the balls the field is zero, so we only have to trace the overlapping
+    // intervals and can skip the gaps
+    let mut intervals = intervals.iter().peekable();
+    while let Some((t_min, mut t_max)) = intervals.next() {
+        while let Some((_, next_max)) = intervals.next_if(|(next_min, _)| *next_min <= t_max) {
+            t_max = Float::max(t_max, next_max);
+        }
+
+        // The last interval also covers the dropped ones, which are farther along
+        if intervals.peek().is_none() {
+            t_max = t_last;
+        }
+
+        let found = push_implicit_hits(
+            origin,
+            ray,
+            t_min,
+            t_max,
+            distance,
+            metaballs.material,
+            hits,
+        );
+
+        if found {
+            return;
+        }
+    }
+}
+
+// Positive above the graph and negative below it
+fn graph_value<V: Vector>(graph: &GraphObject<V>, point: &V) -> Float {
+    const MAX_VARIABLES: usize = 16;
+
+    let local = *point - graph.origin;
+    let components = local.components();
+
+    let mut variables = [0.0; MAX_VARIABLES];
+    let inputs = components
+        .iter()
+        .enumerate()
+        .filter(|(axis, _)| *axis != graph.height_axis);
+    for (variable, (_, c)) in variables.iter_mut().zip(inputs) {
+        *variable = *c;
+    }
+
+    components[graph.height_axis] - graph.expression.evaluate(&variables)
+}
+
+fn test_graph_intersection<V: Vector, H: Hits<V>>(
+    origin: &V,
+    ray: &V,
+    graph: &GraphObject<V>,
+    hits: &mut H,
+) {
+    const STEPS: usize = 256;
+    const MAX_DISTANCE: Float = 100.0;
+    const EPSILON: Float = 1e-4;
+
+    // Clip the ray to the domain, it is unbounded along the height axis
+    let local_origin = *origin - graph.origin;
+    let mut t_min: Float = 0.0;
+    let mut t_max = MAX_DISTANCE;
+
+    for (axis, (o, d)) in local_origin
+        .components()
+        .iter()
+        .zip(ray.components())
+        .enumerate()
+    {
+        if axis == graph.height_axis {
+            continue;
+        }
+
+        if *d == 0.0 {
+            if o.abs() > graph.extent {
+                return;
+            }
+        } else {
+            let t1 = (-graph.extent - o) / d;
+            let t2 = (graph.extent - o) / d;
+            t_min = Float::max(t_min, Float::min(t1, t2));
+            t_max = Float::min(t_max, Float::max(t1, t2));
+        }
+    }
+
+    if t_min >= t_max {
+        return;
+    }
+
+    // March in fixed steps until the sign changes and refine the crossings by bisection
+    let value_at = |t: Float| graph_value(graph, &(*origin + *ray * t));
+    let step = (t_max - t_min) / STEPS as Float;
+    let mut previous_t = t_min;
+    let mut previous = value_at(t_min);
+
+    for i in 1..=STEPS {
+        let t = t_min + step * i as Float;
+        let value = value_at(t);
+
+        // Rays starting on the graph (like shadow rays) don't hit it right away
+        if previous.abs() > EPSILON && previous * value < 0.0 {
+            let sign = previous.signum();
+            let t = bisect(origin, ray, previous_t, t, &|p: &V| {
+                sign * graph_value(graph, p)
+            });
+            let hit = *origin + *ray * t;
+
+            // Everything below the graph counts as inside
+            hits.push(Intersection {
+                position: hit,
+                normal: gradient_normal(&hit, |p: &V| graph_value(graph, p)),
+                distance: (hit - *origin).length(),
+                material: graph.material,
+                inside: previous < 0.0,
+            });
+        }
+
+        previous_t = t;
+        previous = value;
+    }
+}
+
+fn sphere_span<V: Vector>(
+    origin: &V,
+    ray: &V,
+    center: &V,
+    radius: Float,
+    material: usize,
+) -> Option<Span<V>> {
+    let interval = sphere_interval(origin, ray, center, radius)?;
+
+    Some(sphere_interval_span(
+        origin, ray, center, interval, material,
+    ))
+}
+
+fn sphere_interval_span<V: Vector>(
+    origin: &V,
+    ray: &V,
+    center: &V,
+    (t_enter, t_exit): (Float, Float),
+    material: usize,
+) -> Span<V> {
+    let hit = |t: Float| SpanHit {
+        t,
+        normal: (*origin + *ray * t - *center).normalize(),
+        material,
+    };
+
+    Span {
+        enter: hit(t_enter),
+        exit: hit(t_exit),
+    }
+}
+
+// Spans of a CSG tree along a ray, for very busy trees the farthest ones are dropped
+type Spans<V> = SortedBuffer<Span<V>, MAX_SPANS>;
+
+const MAX_SPANS: usize = 16;
+
+// Combines two sorted lists of disjoint spans by walking along all of their boundaries
+fn combine_spans<V: Vector>(a: &Spans<V>, b: &Spans<V>, operation: CsgOperation) -> Spans<V> {
+    let boundaries = |(_, span): (Float, Span<V>)| [(span.enter, true), (span.exit, false)];
+    let mut boundaries_a = a.iter().flat_map(boundaries).peekable();
+    let mut boundaries_b = b.iter().flat_map(boundaries).peekable();
+
+    let mut result = Spans::new();
+    let mut in_a = false;
+    let mut in_b = false;
+    let mut enter: Option<SpanHit<V>> = None;
+
+    loop {
+        let from_a = match (boundaries_a.peek(), boundaries_b.peek()) {
+            (Some((hit_a, _)), Some((hit_b, _))) => hit_a.t <= hit_b.t,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        let (mut hit, entering) = if from_a {
+            boundaries_a.next().unwrap()
+        } else {
+            boundaries_b.next().unwrap()
+        };
+
+        if from_a {
+            in_a = entering;
+        } else {
+            in_b = entering;
+        }
+
+        let inside = match operation {
+            CsgOperation::Union => in_a || in_b,
+            CsgOperation::Intersection => in_a && in_b,
+            CsgOperation::Difference => in_a && !in_b,
+        };
+
+        // Entering the subtracted object means leaving the result, so the normal flips
+        if operation == CsgOperation::Difference && !from_a {
+            hit.normal = hit.normal * -1.0;
+        }
+
+        match enter.take() {
+            None if inside => enter = Some(hit),
+            Some(span_enter) if !inside => result.insert(
+                span_enter.t,
+                Span {
+                    enter: span_enter,
+                    exit: hit,
+                },
+            ),
+            state => enter = state,
+        }
+    }
+
+    result
+}
+
+fn csg_spans<V: Vector>(tree: &CsgTree<V>, index: usize, aabb_ray: &AabbRay<V>) -> Spans<V> {
+    let mut spans = Spans::new();
+    let span = match &tree.nodes[index] {
+        CsgNode::Sphere(center, radius, material) => {
+            sphere_span(&aabb_ray.origin, &aabb_ray.dir, center, *radius, *material)
+        }
+        CsgNode::Aabb(aabb) => aabb_span(aabb_ray, aabb),
+        CsgNode::Operation(operation, a, b) => {
+            return combine_spans(
+                &csg_spans(tree, *a, aabb_ray),
+                &csg_spans(tree, *b, aabb_ray),
+                *operation,
+            )
+        }
+    };
+
+    if let Some(span) = span {
+        spans.insert(span.enter.t, span);
+    }
+    spans
+}
+
+fn test_csg_intersection<V: Vector, H: Hits<V>>(
+    aabb_ray: &AabbRay<V>,
+    tree: &CsgTree<V>,
+    hits: &mut H,
+) {
+    if tree.nodes.is_empty() {
+        return;
+    }
+
+    for (_, span) in csg_spans(tree, tree.nodes.len() - 1, aabb_ray).iter() {
+        span.push_hits(&aabb_ray.origin, &aabb_ray.dir, hits);
+    }
+}
+
+// Walks through the cells of a grid of unit cubes in the order the ray passes them
+struct GridTraversal<V: Vector> {
+    cell: V,
+    step: V,
+    // Distance along the ray to the next cell boundary for each axis
+    t_next: V,
+    t_delta: V,
+    t: Float,
+}
+
+impl<V: Vector> GridTraversal<V> {
+    fn new(origin: &V, ray: &V, t_start: Float) -> Self {
+        let start = *origin + *ray * t_start;
+        let cell = V::from_iter(start.components().iter().map(|c| c.floor()));
+        let step = V::from_iter(ray.components().iter().map(|c| c.signum()));
+
+        let t_next = V::from_iter(
+            start
+                .components()
+                .iter()
+                .zip(cell.components())
+                .zip(ray.components())
+                .map(|((s, c), r)| {
+                    if *r > 0.0 {
+                        t_start + (c + 1.0 - s) / r
+                    } else if *r < 0.0 {
+                        t_start + (c - s) / r
+                    } else {
+                        Float::INFINITY
+                    }
+                }),
+        );
+        let t_delta = V::from_iter(ray.components().iter().map(|r| (1.0 / r).abs()));
+
+        Self {
+            cell,
+            step,
+            t_next,
+            t_delta,
+            t: t_start,
+        }
+    }
+
+    // The current cell and the range of the ray inside of it, then moves on to the next
+    fn next(&mut self) -> (V, Float, Float) {
+        let (axis, t_exit) = self
+            .t_next
+            .components()
+            .iter()
+            .cloned()
+            .enumerate()
+            .fold((0, Float::INFINITY), |a, b| if b.1 < a.1 { b } else { a });
+
+        let current = (self.cell, self.t, t_exit);
+
+        self.cell.components_mut()[axis] += self.step.components()[axis];
+        self.t_next.components_mut()[axis] += self.t_delta.components()[axis];
+        self.t = t_exit;
+
+        current
+    }
+}
+
+// Regular grid of cells over the bounds of all objects, every cell lists the objects
+// reaching into it
+#[derive(Debug)]
+struct UniformGrid<V: Vector> {
+    bounds: Bounds<V>,
+    cell_size: V,
+    resolution: Vec<usize>,
+    // Objects of the cell `i` are `objects[cells[i]..cells[i + 1]]`
+    cells: Vec<usize>,
+    objects: Vec<usize>,
+}
+
+impl<V: Vector> UniformGrid<V> {
+    fn build(objects: Vec<(Bounds<V>, usize)>) -> Self {
+        // Cells per object, and the limits to keep the memory in check in high dimensions
+        const DENSITY: Float = 2.0;
+        const MAX_CELLS: usize = 1 << 18;
+        const MAX_RESOLUTION: usize = 128;
+
+        let bounds = objects
+            .iter()
+            .fold(Bounds::empty(), |a, (bounds, _)| a.union(bounds));
+
+        if objects.is_empty() {
+            return UniformGrid {
+                bounds,
+                cell_size: V::pad(&[], 1.0),
+                resolution: vec![],
+                cells: vec![0],
+                objects: vec![],
+            };
+        }
+
+        // Cubic cells, as many as the density asks for
+        let extent = bounds.max - bounds.min;
+        let dimension = extent.components().len() as Float;
+        let volume: Float = extent.components().iter().product();
+        let mut cell_length =
+            Float::powf(volume / (DENSITY * objects.len() as Float), 1.0 / dimension);
+
+        let resolution = loop {
+            let resolution: Vec<usize> = extent
+                .components()
+                .iter()
+                .map(|e| ((e / cell_length).ceil() as usize).clamp(1, MAX_RESOLUTION))
+                .collect();
+
+            // Flat scenes have no volume, the cells would be tiny
+            if cell_length > 0.0 && resolution.iter().product::<usize>() <= MAX_CELLS {
+                break resolution;
+            }
+
+            cell_length = if cell_length > 0.0 {
+                cell_length * 1.25
+            } else {
+                extent.components().iter().cloned().fold(0.0, Float::max) / MAX_RESOLUTION as Float
+            };
+        };
+
+        let cell_size = V::from_iter(
+            extent
+                .components()
+                .iter()
+                .zip(&resolution)
+                .map(|(e, r)| e / *r as Float),
+        );
+
+        let mut grid = UniformGrid {
+            bounds,
+            cell_size,
+            resolution,
+            cells: vec![],
+            objects: vec![],
+        };
+
+        let mut cells: Vec<Vec<usize>> = vec![vec![]; grid.resolution.iter().product()];
+        for (bounds, object) in &objects {
+            let min = grid.cell_of(&bounds.min);
+            let max = grid.cell_of(&bounds.max);
+
+            // Count through all cells from `min` to `max` like an odometer
+            let mut cell = min.clone();
+            'cells: loop {
+                cells[grid.index(&cell)].push(*object);
+
+                for axis in 0..cell.len() {
+                    if cell[axis] < max[axis] {
+                        cell[axis] += 1;
+                        continue 'cells;
+                    }
+                    cell[axis] = min[axis];
+                }
+
+                break;
+            }
+        }
+
+        grid.cells.push(0);
+        for objects in cells {
+            grid.objects.extend(objects);
+            grid.cells.push(grid.objects.len());
+        }
+
+        grid
+    }
+
+    fn cell_of(&self, point: &V) -> Vec<usize> {
+        point
+            .components()
+            .iter()
+            .zip(self.bounds.min.components())
+            .zip(self.cell_size.components())
+            .zip(&self.resolution)
+            .map(|(((p, min), size), resolution)| {
+                (((p - min) / size).max(0.0) as usize).min(resolution - 1)
+            })
+            .collect()
+    }
+
+    fn index(&self, cell: &[usize]) -> usize {
+        cell.iter()
+            .zip(&self.resolution)
+            .fold(0, |index, (c, resolution)| index * resolution + c)
+    }
+
+    // Calls `visit` for the objects of every cell the ray passes through, in order, with
+    // the range of the ray inside of the cell. It returns how far along the ray objects
+    // still matter, cells behind are skipped.
+    fn traverse<F: FnMut(usize, Float, Float) -> Float>(&self, ray: &AabbRay<V>, mut visit: F) {
+        let (t_start, t_end) = match self.bounds.clip(&ray.origin, &ray.dir_inverse) {
+            Some((t_start, t_end)) if t_end > 0.0 => (Float::max(t_start, 0.0), t_end),
+            _ => return,
+        };
+
+        // In grid coordinates the cells are unit cubes
+        let local_origin = (ray.origin - self.bounds.min) / self.cell_size;
+        let local_ray = ray.dir / self.cell_size;
+        let mut cells = GridTraversal::new(&local_origin, &local_ray, t_start);
+        let mut cell = [0; MAX_DIMENSION];
+        let cell = &mut cell[..self.resolution.len()];
+        let mut t_end = t_end;
+
+        loop {
+            let (position, t_enter, t_exit) = cells.next();
+            if t_enter > t_end || t_exit == Float::INFINITY {
+                return;
+            }
+
+            // The first and last steps might be just outside of the grid
+            let inside = position
+                .components()
+                .iter()
+                .zip(&self.resolution)
+                .zip(cell.iter_mut())
+                .all(|((p, resolution), c)| {
+                    *c = *p as usize;
+                    *p >= 0.0 && *c < *resolution
+                });
+
+            if inside {
+                let index = self.index(cell);
+
+                for object in &self.objects[self.cells[index]..self.cells[index + 1]] {
+                    t_end = Float::min(t_end, visit(*object, t_enter, t_exit));
+                    if t_end <= 0.0 {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn test_lattice_intersection<V: Vector, H: Hits<V>>(
+    origin: &V,
+    ray: &V,
+    lattice: &LatticeObject<V>,
+    hits: &mut H,
+) {
+    // Walk through the cells in lattice coordinates
+    let local_origin = lattice.inverse.transform(&(*origin - lattice.origin));
+    let local_ray = lattice.inverse.transform(ray);
+    let mut cells = GridTraversal::new(&local_origin, &local_ray, 0.0);
+    let ray_length = ray.length();
+
+    // Ends the walk even if the ray crawls through tiny cells
+    const MAX_CELLS: usize = 4096;
+
+    for _ in 0..MAX_CELLS {
+        let (cell, t_enter, t_exit) = cells.next();
+        // Cells behind opaque hits do not matter either
+        let max_distance = Float::min(lattice.max_distance, hits.max_distance());
+        if t_enter * ray_length > max_distance || t_exit == Float::INFINITY {
+            return;
+        }
+
+        let corner = lattice.origin + lattice.basis.transform(&cell);
+
+        for (offset, index) in &lattice.candidates {
+            let (radius, material) = lattice.motif[*index];
+            let span = match sphere_span(origin, ray, &(corner + *offset), radius, material) {
+                Some(span) => span,
+                None => continue,
+            };
+
+            // Hits outside of the cell are found when we visit their cell
+            for (hit, inside) in [(&span.enter, false), (&span.exit, true)] {
+                if hit.t > 0.0 && hit.t >= t_enter && hit.t < t_exit {
+                    hits.push(hit.to_intersection(origin, ray, inside));
+                }
+            }
+        }
+    }
+}
+
+fn axis_normalize<V: Vector>(v: &V) -> V {
+    let mut most_dominat: Float = 0.0;
+
+    for comp in v.components() {
+        let comp = *comp;
+        if comp.abs() > most_dominat.abs() {
+            most_dominat = comp
+        }
+    }
+
+    let result = v.components().iter().cloned().map(|comp| {
+        if comp == most_dominat {
+            most_dominat.signum()
+        } else {
+            0.0
+        }
+    });
+
+    V::from_iter(result)
+}
+
+fn aabb_span<V: Vector>(ray: &AabbRay<V>, aabb: &Aabb<V>) -> Option<Span<V>> {
+    let interval = aabb_interval(ray, &aabb.min, &aabb.max)?;
+
+    Some(aabb_interval_span(
+        &ray.origin,
+        &ray.dir,
+        &aabb.center,
+        aabb.material,
+        interval,
+    ))
+}
+
+fn aabb_interval<V: Vector>(ray: &AabbRay<V>, min: &V, max: &V) -> Option<(Float, Float)> {
+    let mut clamped_min = -Float::INFINITY;
+    let mut clamped_max = Float::INFINITY;
+
+    for (((origin_comp, ray_inverse), bounds_min), bounds_max) in ray
+        .origin
+        .components()
+        .into_iter()
+        .zip(ray.dir_inverse.components())
+        .zip(min.components())
+        .zip(max.components())
+    {
+        let tx1 = (bounds_min - origin_comp) * ray_inverse;
+        let tx2 = (bounds_max - origin_comp) * ray_inverse;
+
+        clamped_min = Float::max(clamped_min, Float::min(tx1, tx2));
+        clamped_max = Float::min(clamped_max, Float::max(tx1, tx2));
+    }
+
+    // cube is in line of the ray, not necessarily in front through
+    if clamped_min >= clamped_max {
+        return None;
+    }
+
+    Some((clamped_min, clamped_max))
+}
+
+fn aabb_interval_span<V: Vector>(
+    origin: &V,
+    ray: &V,
+    center: &V,
+    material: usize,
+    (t_enter, t_exit): (Float, Float),
+) -> Span<V> {
+    let hit_in = *origin + (*ray * t_enter);
+    let hit_out = *origin + (*ray * t_exit);
+
+    Span {
+        enter: SpanHit {
+            t: t_enter,
+            normal: axis_normalize(&(hit_in - *center)),
+            material,
+        },
+        exit: SpanHit {
+            t: t_exit,
+            normal: axis_normalize(&(hit_out - *center)),
+            material,
+        },
+    }
+}
+
+fn test_aabb_intersection<V: Vector, H: Hits<V>>(
+    ray: &AabbRay<V>,
+    aabbs: &Aabbs<V>,
+    index: usize,
+    hits: &mut H,
+) {
+    if let Some(interval) = aabb_interval(ray, &aabbs.mins[index], &aabbs.maxs[index]) {
+        let span = aabb_interval_span(
+            &ray.origin,
+            &ray.dir,
+            &aabbs.centers[index],
+            aabbs.materials[index],
+            interval,
+        );
+        span.push_hits(&ray.origin, &ray.dir, hits);
+    }
+}
+
+// Hands the hits along the ray to `hits` in no particular order, objects beyond its
+// `max_distance` are skipped
+fn find_intersections<V: Vector, H: Hits<V>>(
+    world: &DimensionalWorld<V>,
+    origin: &V,
+    ray: &V,
+    hits: &mut H,
+) {
+    find_intersections_with(world, None, origin, ray, hits);
+}
+
+// Same as `find_intersections` but skips the bounded objects which are not `visible`
+fn find_intersections_with<V: Vector, H: Hits<V>>(
+    world: &DimensionalWorld<V>,
+    visible: Option<&[bool]>,
+    origin: &V,
+    ray: &V,
+    hits: &mut H,
+) {
+    let aabb_ray = AabbRay::<V> {
+        origin: origin.clone(),
+        dir: ray.clone(),
+        dir_inverse: V::pad(&[], 1.0) / *ray,
+    };
+    // The accelerators measure along the ray, which might not be exactly normalized
+    let ray_length = ray.length();
+
+    for object in &world.unbounded {
+        if hits.max_distance() <= 0.0 {
+            return;
+        }
+        test_object_intersection(world, *object, &aabb_ray, hits);
+    }
+
+    match &world.accelerator {
+        Accelerator::BruteForce => {
+            for (index, (_, object)) in world.bounded.iter().enumerate() {
+                if hits.max_distance() <= 0.0 {
+                    return;
+                }
+                if is_visible(visible, index) {
+                    test_object_intersection(world, *object, &aabb_ray, hits);
+                }
+            }
+        }
+        Accelerator::Bvh(bvh) => bvh.traverse(origin, &aabb_ray.dir_inverse, |index| {
+            if is_visible(visible, *index) {
+                test_object_intersection(world, world.bounded[*index].1, &aabb_ray, hits);
+            }
+            hits.max_distance() / ray_length
+        }),
+        Accelerator::Grid(grid) => grid.traverse(&aabb_ray, |index, t_enter, t_exit| {
+            if is_visible(visible, index) {
+                let mut cell_hits = CellHits {
+                    hits: &mut *hits,
+                    origin: *origin,
+                    ray: *ray,
+                    t_enter,
+                    t_exit,
+                };
+                test_object_intersection(world, world.bounded[index].1, &aabb_ray, &mut cell_hits);
+            }
+            hits.max_distance() / ray_length
+        }),
+    }
+}
+
+fn test_object_intersection<V: Vector, H: Hits<V>>(
+    world: &DimensionalWorld<V>,
+    object: Object,
+    aabb_ray: &AabbRay<V>,
+    hits: &mut H,
+) {
+    let origin = &aabb_ray.origin;
+    let ray = &aabb_ray.dir;
+
+    match object {
+        Object::Sphere(i) => test_sphere_intersection(origin, ray, &world.spheres, i, hits),
+        Object::Aabb(i) => test_aabb_intersection(aabb_ray, &world.aabbs, i, hits),
+        Object::Cone(i) => test_cone_intersection(origin, ray, &world.cones[i], hits),
+        Object::Capsule(i) => test_capsule_intersection(origin, ray, &world.capsules[i], hits),
+        Object::Simplex(i) => test_simplex_intersection(origin, ray, &world.simplices[i], hits),
+        Object::Complex(i) => {
+            test_complex_intersection(origin, ray, &world.vertices, &world.complexes[i], hits)
+        }
+        Object::Polytope(i) => test_polytope_intersection(origin, ray, &world.polytopes[i], hits),
+        Object::Torus(i) => test_torus_intersection(origin, ray, &world.tori[i], hits),
+        Object::Sdf(i) => test_sdf_intersection(origin, ray, &world.sdfs[i], hits),
+        Object::Csg(i) => test_csg_intersection(aabb_ray, &world.csgs[i], hits),
+        Object::Metaballs(i) => test_metaball_intersection(origin, ray, &world.metaballs[i], hits),
+        Object::Graph(i) => test_graph_intersection(origin, ray, &world.graphs[i], hits),
+        Object::Lattice(i) => test_lattice_intersection(origin, ray, &world.lattices[i], hits),
+        Object::Instance(i) => test_instance_intersection(world, origin, ray, i, hits),
+    }
+}
+
+fn test_instance_intersection<V: Vector, H: Hits<V>>(
+    world: &DimensionalWorld<V>,
+    origin: &V,
+    ray: &V,
+    index: usize,
+    hits: &mut H,
+) {
+    let instance = &world.instances[index];
+
+    // Trace the prototype in its own space
+    let local_origin = instance
+        .inverse
+        .transform(&(*origin - instance.translation));
+    let local_ray = instance.inverse.transform(ray);
+    let local_ray = local_ray / local_ray.length();
+
+    let prototype = &world.prototypes[instance.prototype];
+    let mut instance_hits = InstanceHits {
+        instance,
+        origin: *origin,
+        hits,
+    };
+
+    find_intersections(prototype, &local_origin, &local_ray, &mut instance_hits);
+}
+
+// Number of rays traced together, one per lane of `Lanes`
+pub const PACKET_SIZE: usize = 4;
+// Highest dimension a packet can hold, see `update`
+const MAX_DIMENSION: usize = 9;
+
+// One value for each ray of a packet
+type Lanes = FixedVector<PACKET_SIZE>;
+
+// Coherent rays from a common origin. The directions are also stored per axis, so
+// the sphere and box tests handle all rays of the packet at once.
+struct RayPacket<V: Vector> {
+    origin: V,
+    rays: [AabbRay<V>; PACKET_SIZE],
+    dirs: [Lanes; MAX_DIMENSION],
+    dir_inverses: [Lanes; MAX_DIMENSION],
+    lengths: Lanes,
+}
+
+impl<V: Vector> RayPacket<V> {
+    fn new(origin: V, rays: [V; PACKET_SIZE]) -> Self {
+        // Higher axes would be dropped here and indexed out of bounds later, `update` only
+        // renders up to dimension 9
+        debug_assert!(origin.components().len() <= MAX_DIMENSION);
+
+        let mut dirs = [Lanes::new(); MAX_DIMENSION];
+        for (axis, dir) in dirs.iter_mut().enumerate().take(origin.components().len()) {
+            *dir = Lanes::from_iter(rays.iter().map(|ray| ray.components()[axis]));
+        }
+
+        RayPacket {
+            origin,
+            rays: rays.map(|dir| AabbRay {
+                origin,
+                dir,
+                dir_inverse: V::pad(&[], 1.0) / dir,
+            }),
+            dirs,
+            dir_inverses: dirs.map(|dir| Lanes::pad(&[], 1.0) / dir),
+            lengths: Lanes::from_iter(rays.iter().map(|ray| ray.length())),
+        }
+    }
+
+    fn axes(&self) -> usize {
+        self.origin.components().len()
+    }
+
+    // How far along each ray objects still matter
+    fn limits(&self, hits: &[HitBuffer<'_, V>; PACKET_SIZE]) -> Lanes {
+        Lanes::from_iter(
+            hits.iter()
+                .zip(self.lengths.components())
+                .map(|(hits, length)| hits.max_distance() / length),
+        )
+    }
+
+    // Range of each ray inside of the box, empty where `t_enter > t_exit`
+    fn clip(&self, min: &V, max: &V) -> (Lanes, Lanes) {
+        let mut t_enter = Lanes::pad(&[], -Float::INFINITY);
+        let mut t_exit = Lanes::pad(&[], Float::INFINITY);
+
+        for axis in 0..self.axes() {
+            let origin = self.origin.components()[axis];
+            let t1 = self.dir_inverses[axis] * (min.components()[axis] - origin);
+            let t2 = self.dir_inverses[axis] * (max.components()[axis] - origin);
+
+            t_enter = lanes_max(&t_enter, &lanes_min(&t1, &t2));
+            t_exit = lanes_min(&t_exit, &lanes_max(&t1, &t2));
+        }
+
+        (t_enter, t_exit)
+    }
+
+    // Whether any ray passes through the box in front of its origin and before its limit
+    fn is_hit(&self, bounds: &Bounds<V>, limits: &Lanes) -> bool {
+        let (t_enter, t_exit) = self.clip(&bounds.min, &bounds.max);
+
+        (0..PACKET_SIZE).any(|lane| {
+            let t_enter = t_enter.components()[lane];
+            let t_exit = t_exit.components()[lane];
+            t_enter <= t_exit && t_exit > 0.0 && t_enter <= limits.components()[lane]
+        })
+    }
+}
+
+fn lanes_min(a: &Lanes, b: &Lanes) -> Lanes {
+    Lanes::from_iter(
+        a.components()
+            .iter()
+            .zip(b.components())
+            .map(|(a, b)| a.min(*b)),
+    )
+}
+
+fn lanes_max(a: &Lanes, b: &Lanes) -> Lanes {
+    Lanes::from_iter(
+        a.components()
+            .iter()
+            .zip(b.components())
+            .map(|(a, b)| a.max(*b)),
+    )
+}
+
+// Same as `find_intersections` for every ray of the packet. The grid walks each ray on
+// its own since the rays go through different cells.
+fn find_packet_intersections<V: Vector>(
+    world: &DimensionalWorld<V>,
+    visible: Option<&[bool]>,
+    packet: &RayPacket<V>,
+    hits: &mut [HitBuffer<'_, V>; PACKET_SIZE],
+) {
+    if let Accelerator::Grid(_) = world.accelerator {
+        for (ray, hits) in packet.rays.iter().zip(hits.iter_mut()) {
+            find_intersections_with(world, visible, &ray.origin, &ray.dir, hits);
+        }
+        return;
+    }
+
+    for object in &world.unbounded {
+        test_packet_intersection(world, *object, packet, hits);
+    }
+
+    match &world.accelerator {
+        Accelerator::BruteForce => {
+            for (index, (_, object)) in world.bounded.iter().enumerate() {
+                if is_visible(visible, index) {
+                    test_packet_intersection(world, *object, packet, hits);
+                }
+            }
+        }
+        Accelerator::Bvh(bvh) => bvh.walk(
+            hits,
+            |hits, bounds| packet.is_hit(bounds, &packet.limits(hits)),
+            |hits, index| {
+                if is_visible(visible, *index) {
+                    test_packet_intersection(world, world.bounded[*index].1, packet, hits);
+                }
+            },
+        ),
+        Accelerator::Grid(_) => unreachable!(),
+    }
+}
+
+// Spheres and boxes are tested for the whole packet, everything else ray by ray
+fn test_packet_intersection<V: Vector>(
+    world: &DimensionalWorld<V>,
+    object: Object,
+    packet: &RayPacket<V>,
+    hits: &mut [HitBuffer<'_, V>; PACKET_SIZE],
+) {
+    match object {
+        Object::Sphere(i) => test_sphere_packet(packet, &world.spheres, i, hits),
+        Object::Aabb(i) => test_aabb_packet(packet, &world.aabbs, i, hits),
+        _ => {
+            for (ray, hits) in packet.rays.iter().zip(hits.iter_mut()) {
+                if hits.max_distance() > 0.0 {
+                    test_object_intersection(world, object, ray, hits);
+                }
+            }
+        }
+    }
+}
+
+fn test_sphere_packet<V: Vector>(
+    packet: &RayPacket<V>,
+    spheres: &Spheres<V>,
+    index: usize,
+    hits: &mut [HitBuffer<'_, V>; PACKET_SIZE],
+) {
+    let center = &spheres.centers[index];
+    let radius = spheres.radii[index];
+
+    // Same as `sphere_interval`, the origin is shared so only `tc` differs between rays
+    let origin_to_center = *center - packet.origin;
+    let lengths_squared = packet.lengths * packet.lengths;
+    let mut tc = Lanes::new();
+    for (dir, component) in packet.dirs.iter().zip(origin_to_center.components()) {
+        tc = tc + *dir * *component;
+    }
+    let tc = tc / lengths_squared;
+    let d_squared = Lanes::pad(&[], origin_to_center.sum_of_squares()) - tc * tc * lengths_squared;
+    let radius_squared = radius * radius;
+
+    for (lane, hits) in hits.iter_mut().enumerate() {
+        let d_squared = d_squared.components()[lane];
+        if d_squared > radius_squared {
+            continue;
+        }
+
+        let tc = tc.components()[lane];
+        let t1c = Float::sqrt((radius_squared - d_squared) / lengths_squared.components()[lane]);
+        let ray = &packet.rays[lane].dir;
+
+        let span = sphere_interval_span(
+            &packet.origin,
+            ray,
+            center,
+            (tc - t1c, tc + t1c),
+            spheres.materials[index],
+        );
+        span.push_hits(&packet.origin, ray, hits);
+    }
+}
+
+fn test_aabb_packet<V: Vector>(
+    packet: &RayPacket<V>,
+    aabbs: &Aabbs<V>,
+    index: usize,
+    hits: &mut [HitBuffer<'_, V>; PACKET_SIZE],
+) {
+    let (t_enter, t_exit) = packet.clip(&aabbs.mins[index], &aabbs.maxs[index]);
+
+    for (lane, hits) in hits.iter_mut().enumerate() {
+        let t_enter = t_enter.components()[lane];
+        let t_exit = t_exit.components()[lane];
+
+        // Same as `aabb_span`
+        if t_enter >= t_exit {
+            continue;
+        }
+
+        let ray = &packet.rays[lane].dir;
+        let span = aabb_interval_span(
+            &packet.origin,
+            ray,
+            &aabbs.centers[index],
+            aabbs.materials[index],
+            (t_enter, t_exit),
+        );
+        span.push_hits(&packet.origin, ray, hits);
+    }
+}
+
+// Start of a secondary ray leaving a hit in `direction`. It is moved off the surface
+// to the side the ray goes to, otherwise rounding errors let it hit the surface again
+// right away. The errors grow with the magnitude of the position, so does the offset.
+fn offset_origin<V: Vector>(hit: &Intersection<V>, direction: &V) -> V {
+    const RAY_OFFSET: Float = 1e-3;
+
+    let magnitude = hit
+        .position
+        .components()
+        .iter()
+        .fold(1.0, |magnitude, c| Float::max(magnitude, c.abs()));
+    let offset = hit.normal * (RAY_OFFSET * magnitude);
+
+    if hit.normal.dot(direction) >= 0.0 {
+        hit.position + offset
+    } else {
+        hit.position - offset
+    }
+}
+
+// Light arriving through all objects along the ray
+fn get_light_color<V: Vector>(
+    world: &DimensionalWorld<V>,
+    origin: &V,
+    ray: &V,
+    light_color: Color,
+) -> Color {
+    let mut transmittance = Transmittance {
+        color: light_color,
+        materials: &world.materials,
+    };
+    find_intersections(world, origin, ray, &mut transmittance);
+
+    transmittance.color
+}
+
+fn trace<V: Vector>(
+    world: &DimensionalWorld<V>,
+    visible: Option<&[bool]>,
+    cam_pos: &V,
+    ray: &V,
+    reflection_bounces: usize,
+) -> Color {
+    let mut hits = HitBuffer::new(&world.materials);
+    find_intersections_with(world, visible, cam_pos, ray, &mut hits);
+
+    shade(world, ray, &hits, reflection_bounces)
+}
+
+// Color seen along the ray through its hits
+fn shade<V: Vector>(
+    world: &DimensionalWorld<V>,
+    ray: &V,
+    hits: &HitBuffer<'_, V>,
+    reflection_bounces: usize,
+) -> Color {
+    // Front to back, `transmittance` is how much of what lies behind still shows through
+    let mut color = Color::rgb(0.0, 0.0, 0.0);
+    let mut transmittance = 1.0;
+
+    for hit in hits.iter() {
+        let surface = &world.materials[hit.material];
+
+        // Translucent objects are blended in once, by their front. Only refracting ones
+        // show their back as well, where the light bends out again.
+        if hit.inside && surface.refraction.is_none() && surface.color.alpha() < 1.0 {
+            continue;
+        }
+
+        let mut hit_color = surface.color;
+
+        // From the inside we see the back of the surface
+        let normal = if hit.inside {
+            hit.normal * -1.0
+        } else {
+            hit.normal
+        };
+
+        // Ambient light color
+        let mut lights_color = Color::rgba(0.3, 0.3, 0.3, 1.0);
+        let mut highlights_color = Color::rgb(0.0, 0.0, 0.0);
+
+        for (light_pos, light) in &world.lights {
+            let hit_to_light = (*light_pos - hit.position).normalize();
+
+            let shadow_origin = offset_origin(hit, &hit_to_light);
+            let mut color = get_light_color(world, &shadow_origin, &hit_to_light, light.color);
+
+            let angle = normal.dot(&hit_to_light);
+
+            let strength = highlight_strength(ray, &normal, &hit_to_light, surface.shininess);
+            if strength > 0.0 {
+                let specular = &surface.specular;
+
+                highlights_color.combine(&Color::rgba(
+                    color.red() * specular.red(),
+                    color.green() * specular.green(),
+                    color.blue() * specular.blue(),
+                    color.alpha() * specular.alpha() * strength,
+                ));
+            }
+
+            // The more the brightness of the light is influenced by the angle the softer curves will look
+            let brightness = Float::max(angle * 0.8 + 0.2, 0.0);
+            color.adjust_brightness(brightness);
+
+            lights_color.combine(&color);
+        }
+
+        hit_color.apply(&lights_color);
+        // Highlights are not tinted by the surface
+        hit_color.combine(&highlights_color);
+
+        if reflection_bounces > 0 && surface.reflection > 0.0 {
+            let ray_reflection = *ray - (normal * 2.0 * ray.dot(&normal));
+            let mut color = trace(
+                world,
+                None,
+                &offset_origin(hit, &ray_reflection),
+                &ray_reflection,
+                reflection_bounces - 1,
+            );
+            color.set_alpha(surface.reflection);
+            hit_color.mix(&color);
+        }
+
+        let alpha = surface.color.alpha();
+        hit_color.set_alpha(alpha * transmittance);
+        color.combine(&hit_color);
+
+        transmittance *= 1.0 - alpha;
+        if transmittance <= 0.0 {
+            return color;
+        }
+
+        // What shines through comes along the bent ray instead of the hits behind
+        if let Some(refraction) = surface.refraction {
+            if reflection_bounces > 0 {
+                let mut behind =
+                    refracted_color(world, ray, hit, &normal, refraction, reflection_bounces - 1);
+                behind.set_alpha(transmittance);
+                color.combine(&behind);
+
+                return color;
+            }
+        }
+    }
+
+    let mut background = BG_COLOR;
+    background.set_alpha(transmittance);
+    color.combine(&background);
+
+    color
+}
+
+// Light passing through the surface towards `ray`. Snell's law holds in the plane of the
+// ray and the normal, so it works the same in N dimensions. The Fresnel equations (in
+// Schlick's approximation) split it into a reflected and a refracted part.
+fn refracted_color<V: Vector>(
+    world: &DimensionalWorld<V>,
+    ray: &V,
+    hit: &Intersection<V>,
+    normal: &V,
+    refraction: Float,
+    reflection_bounces: usize,
+) -> Color {
+    // Outside of all objects is vacuum, `normal` faces the ray
+    let (n_from, n_to) = if hit.inside {
+        (refraction, 1.0)
+    } else {
+        (1.0, refraction)
+    };
+    let cos_in = Float::min(-ray.dot(normal), 1.0);
+
+    let ray_reflection = *ray + *normal * (2.0 * cos_in);
+    let mut reflected = trace(
+        world,
+        None,
+        &offset_origin(hit, &ray_reflection),
+        &ray_reflection,
+        reflection_bounces,
+    );
+
+    let ray_refraction = match refract(ray, normal, n_from / n_to) {
+        Some(ray_refraction) => ray_refraction,
+        // Total internal reflection
+        None => return reflected,
+    };
+    let cos_out = -ray_refraction.dot(normal);
+    let mut color = trace(
+        world,
+        None,
+        &offset_origin(hit, &ray_refraction),
+        &ray_refraction,
+        reflection_bounces,
+    );
+
+    let r0 = ((n_from - n_to) / (n_from + n_to)).powi(2);
+    let cos = if n_from > n_to { cos_out } else { cos_in };
+    reflected.set_alpha(r0 + (1.0 - r0) * (1.0 - cos).powi(5));
+    color.mix(&reflected);
+
+    color
+}
+
+// Blinn-Phong, brightest where the normal is halfway between the light and the eye,
+// i.e. where the ray is reflected straight to the light. Nothing lights up when the
+// light is behind the surface.
+fn highlight_strength<V: Vector>(ray: &V, normal: &V, to_light: &V, shininess: Float) -> Float {
+    if normal.dot(to_light) <= 0.0 {
+        return 0.0;
+    }
+
+    let halfway = (*to_light - *ray).normalize();
+    Float::max(normal.dot(&halfway), 0.0).powf(shininess)
+}
+
+// Direction of the ray after passing into a medium with `eta` times lower index of
+// refraction, `None` if it is reflected entirely
+fn refract<V: Vector>(ray: &V, normal: &V, eta: Float) -> Option<V> {
+    let cos_in = Float::min(-ray.dot(normal), 1.0);
+    let cos_out_squared = 1.0 - eta * eta * (1.0 - cos_in * cos_in);
+
+    if cos_out_squared < 0.0 {
+        return None;
+    }
+
+    Some(*ray * eta + *normal * (eta * cos_in - cos_out_squared.sqrt()))
+}
+
+const REFLECTION_BOUNCES: usize = 2;
+
+// Only primary rays are limited to the objects of the view, shadows and reflections
+// might reach anything
+pub fn sample<V: Vector>(
+    world: &DimensionalWorld<V>,
+    view: &View<V>,
+    rel_x: Float,
+    rel_y: Float,
+) -> Color {
+    let camera = &view.camera;
+    let ray = primary_ray(camera, rel_x, rel_y);
+
+    trace(
+        world,
+        view.visible.as_deref(),
+        &camera.position,
+        &ray,
+        REFLECTION_BOUNCES,
+    )
+}
+
+// Samples neighbouring points at once, see `RayPacket`
+pub fn sample_packet<V: Vector>(
+    world: &DimensionalWorld<V>,
+    view: &View<V>,
+    points: [(Float, Float); PACKET_SIZE],
+) -> [Color; PACKET_SIZE] {
+    let camera = &view.camera;
+    let packet = RayPacket::new(
+        camera.position,
+        points.map(|(rel_x, rel_y)| primary_ray(camera, rel_x, rel_y)),
+    );
+
+    let mut hits = [(); PACKET_SIZE].map(|_| HitBuffer::new(&world.materials));
+    find_packet_intersections(world, view.visible.as_deref(), &packet, &mut hits);
+
+    let mut colors = [BG_COLOR; PACKET_SIZE];
+    for (lane, color) in colors.iter_mut().enumerate() {
+        *color = shade(
+            world,
+            &packet.rays[lane].dir,
+            &hits[lane],
+            REFLECTION_BOUNCES,
+        );
+    }
+
+    colors
+}
+
+fn primary_ray<V: Vector>(camera: &Camera<V>, rel_x: Float, rel_y: Float) -> V {
+    let zoom = 1.4;
+    let cam_dir = (camera.center - camera.position).normalize();
+    let cam_dir_ort = V::pad(&[-cam_dir.components()[1], cam_dir.components()[0]], 0.0);
+
+    let centered_x = rel_x * 2.0 - 1.0;
+    let centered_y = rel_y * 2.0 - 1.0;
+    let pos_on_sensor_x = cam_dir_ort * centered_x;
+    let pos_on_sensor_y = V::pad(&[0.0, 0.0, 1.0], 0.0) * centered_y;
+    let pos_on_sensor = pos_on_sensor_x + pos_on_sensor_y;
+
+    (cam_dir * zoom + pos_on_sensor).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{
+        Capsule, Cone, Csg, FunctionGraph, Hypertorus, Lattice, Polytope, RegularPolytope, Sdf,
+        Simplex, SimplicialComplex,
+    };
+    use ndrt_lib::FixedVector;
+
+    impl<V: Vector> Hits<V> for Vec<Intersection<V>> {
+        fn push(&mut self, hit: Intersection<V>) {
+            Vec::push(self, hit);
+        }
+    }
+
+    fn get_all_intersections<V: Vector>(
+        world: &DimensionalWorld<V>,
+        origin: &V,
+        ray: &V,
+    ) -> Vec<Intersection<V>> {
+        let mut all = Vec::with_capacity(8);
+
+        find_intersections(world, origin, ray, &mut all);
+        all.sort_by(|a, b| b.distance.partial_cmp(&a.distance).unwrap());
+
+        all
+    }
+
+    const EPSILON: Float = 1e-3;
+
+    macro_rules! in_all_dimensions {
+        ($test:ident) => {
+            $test::<FixedVector<2>>();
+            $test::<FixedVector<3>>();
+            $test::<FixedVector<4>>();
+            $test::<FixedVector<5>>();
+            $test::<FixedVector<6>>();
+            $test::<FixedVector<7>>();
+            $test::<FixedVector<8>>();
+            $test::<FixedVector<9>>();
+        };
+    }
+
+    fn white() -> Color {
+        Color::rgba(1.0, 1.0, 1.0, 1.0)
+    }
+
+    fn diagonal<V: Vector>() -> V {
+        let v = V::pad(&[], 1.0);
+        v / v.length()
+    }
+
+    fn sphere_world<V: Vector>() -> DimensionalWorld<V> {
+        let mut world = World::new();
+        world.add_sphere(vec![], Sphere::new(2.0, white(), None, None));
+        DimensionalWorld::from_world(&world)
+    }
+
+    fn cube_world<V: Vector>() -> DimensionalWorld<V> {
+        let mut world = World::new();
+        world.add_cube(vec![], Cube::new(2.0, white(), None, None));
+        DimensionalWorld::from_world(&world)
+    }
+
+    fn ray_from_inside_sphere<V: Vector>() {
+        let world = sphere_world::<V>();
+        let ray = diagonal::<V>();
+
+        for origin in &[V::new(), V::pad(&[0.5, -1.0], 0.0)] {
+            let hits = get_all_intersections(&world, origin, &ray);
+
+            assert_eq!(hits.len(), 1);
+            assert!(hits[0].inside);
+            assert!((hits[0].position.length() - 2.0).abs() < EPSILON);
+            assert!(hits[0].normal.dot(&ray) > 0.0);
+        }
+    }
+
+    fn ray_through_sphere<V: Vector>() {
+        let world = sphere_world::<V>();
+        let origin = V::pad(&[-5.0], 0.0);
+        let ray = V::pad(&[1.0], 0.0);
+
+        // Sorted from back to front
+        let hits = get_all_intersections(&world, &origin, &ray);
+        assert_eq!(hits.len(), 2);
+
+        assert!(!hits[1].inside);
+        assert!((hits[1].distance - 3.0).abs() < EPSILON);
+        assert!(hits[1].normal.dot(&ray) < 0.0);
+
+        assert!(hits[0].inside);
+        assert!((hits[0].distance - 7.0).abs() < EPSILON);
+        assert!(hits[0].normal.dot(&ray) > 0.0);
+
+        // Nothing behind the origin
+        let hits = get_all_intersections(&world, &origin, &(ray * -1.0));
+        assert!(hits.is_empty());
+    }
+
+    fn ray_from_inside_cube<V: Vector>() {
+        let world = cube_world::<V>();
+
+        let ray = V::pad(&[1.0], 0.0);
+        let hits = get_all_intersections(&world, &V::pad(&[0.25], 0.0), &ray);
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].inside);
+        assert!((hits[0].distance - 0.75).abs() < EPSILON);
+        assert!((hits[0].normal.dot(&ray) - 1.0).abs() < EPSILON);
+
+        let ray = diagonal::<V>();
+        let origin = V::pad(&[0.5], 0.0);
+        let hits = get_all_intersections(&world, &origin, &ray);
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].inside);
+        assert!((hits[0].position.components()[0] - 1.0).abs() < EPSILON);
+    }
+
+    fn ray_through_cube<V: Vector>() {
+        let world = cube_world::<V>();
+        let origin = V::pad(&[0.0, -4.0], 0.5);
+        let ray = V::pad(&[0.0, 1.0], 0.0);
+
+        let hits = get_all_intersections(&world, &origin, &ray);
+        assert_eq!(hits.len(), 2);
+
+        assert!(!hits[1].inside);
+        assert!((hits[1].distance - 3.0).abs() < EPSILON);
+        assert!(hits[1].normal.dot(&ray) < 0.0);
+
+        assert!(hits[0].inside);
+        assert!((hits[0].distance - 5.0).abs() < EPSILON);
+        assert!(hits[0].normal.dot(&ray) > 0.0);
+    }
+
+    // Opens along x with a half-angle of 45 degrees, so the surface is where |y| = x
+    fn cone_world<V: Vector>(capped: bool) -> DimensionalWorld<V> {
+        let mut world = World::new();
+        let angle = std::f64::consts::FRAC_PI_4 as Float;
+        world.add_cone(
+            vec![],
+            Cone::new(vec![1.0], angle, 2.0, capped, white(), None),
+        );
+        DimensionalWorld::from_world(&world)
+    }
+
+    fn ray_through_cone<V: Vector>() {
+        let world = cone_world::<V>(true);
+        let outwards = |x: Float, y: Float| V::pad(&[x, y], 0.0) / Float::sqrt(x * x + y * y);
+
+        // Across the side, sorted from back to front
+        let ray = V::pad(&[0.0, 1.0], 0.0);
+        let hits = get_all_intersections(&world, &V::pad(&[1.0, -5.0], 0.0), &ray);
+        assert_eq!(hits.len(), 2);
+
+        assert!(!hits[1].inside);
+        assert!((hits[1].distance - 4.0).abs() < EPSILON);
+        assert!((hits[1].normal - outwards(-1.0, -1.0)).length() < EPSILON);
+
+        assert!(hits[0].inside);
+        assert!((hits[0].distance - 6.0).abs() < EPSILON);
+        assert!((hits[0].normal - outwards(-1.0, 1.0)).length() < EPSILON);
+
+        // In through the cap and out through the side
+        let ray = V::pad(&[-1.0], 0.0);
+        let hits = get_all_intersections(&world, &V::pad(&[4.0, 0.5], 0.0), &ray);
+        assert_eq!(hits.len(), 2);
+
+        assert!(!hits[1].inside);
+        assert!((hits[1].distance - 2.0).abs() < EPSILON);
+        assert!((hits[1].normal - V::pad(&[1.0], 0.0)).length() < EPSILON);
+
+        assert!(hits[0].inside);
+        assert!((hits[0].distance - 3.5).abs() < EPSILON);
+        assert!(hits[0].normal.dot(&ray) > 0.0);
+    }
+
+    // Rays with the same direction as a generator line only cross the surface once
+    fn ray_along_cone_generator<V: Vector>() {
+        let world = cone_world::<V>(false);
+        let ray = V::pad(&[1.0, 1.0], 0.0) / Float::sqrt(2.0);
+
+        // Parallel to the upper generator, it enters through the lower one
+        let hits = get_all_intersections(&world, &V::pad(&[0.5, -1.0], 0.0), &ray);
+        assert_eq!(hits.len(), 1);
+        assert!(!hits[0].inside);
+        assert!((hits[0].position - V::pad(&[0.75, -0.75], 0.0)).length() < EPSILON);
+        assert!(hits[0].normal.dot(&ray) < 0.0);
+
+        // Along the generator itself it touches the surface where it reaches the apex
+        let hits = get_all_intersections(&world, &V::pad(&[-1.0, -1.0], 0.0), &ray);
+        assert_eq!(hits.len(), 1);
+        assert!((hits[0].distance - Float::sqrt(2.0)).abs() < EPSILON);
+    }
+
+    // Hypercube with the facets at -1 and 1 in every axis
+    fn hypercube_polytope(dimension: usize) -> Polytope {
+        RegularPolytope::hypercube(dimension).to_polytope(
+            Float::sqrt(dimension as Float),
+            white(),
+            None,
+        )
+    }
+
+    fn ray_through_polytope<V: Vector>() {
+        let dimension = V::new().components().len();
+        let mut world = World::new();
+        world.add_polytope(vec![], hypercube_polytope(dimension));
+        let world = DimensionalWorld::<V>::from_world(&world);
+
+        // Straight through two opposite facets, sorted from back to front. The normals
+        // are only approximately normalized.
+        let ray = V::pad(&[1.0], 0.0);
+        let hits = get_all_intersections(&world, &V::pad(&[-5.0], 0.0), &ray);
+        assert_eq!(hits.len(), 2);
+
+        assert!(!hits[1].inside);
+        assert!((hits[1].distance - 4.0).abs() < EPSILON);
+        assert!(hits[1].normal.dot(&ray) < -0.99);
+
+        assert!(hits[0].inside);
+        assert!((hits[0].distance - 6.0).abs() < EPSILON);
+        assert!(hits[0].normal.dot(&ray) > 0.99);
+
+        // Entering the first half-space is not enough when the others are crossed later
+        let ray = V::pad(&[1.0, 2.0], 0.0) / Float::sqrt(5.0);
+        let hits = get_all_intersections(&world, &V::pad(&[-1.5, -3.0], 0.0), &ray);
+        assert_eq!(hits.len(), 2);
+
+        assert!((hits[1].distance - Float::sqrt(5.0)).abs() < EPSILON);
+        assert!((hits[1].position - V::pad(&[-0.5, -1.0], 0.0)).length() < EPSILON);
+        assert!(hits[1].normal.dot(&V::pad(&[0.0, -1.0], 0.0)) > 0.99);
+
+        assert!((hits[0].distance - 2.0 * Float::sqrt(5.0)).abs() < EPSILON);
+        assert!((hits[0].position - V::pad(&[0.5, 1.0], 0.0)).length() < EPSILON);
+        assert!(hits[0].normal.dot(&V::pad(&[0.0, 1.0], 0.0)) > 0.99);
+
+        // Parallel to a facet but outside of it, and crossing the half-spaces one after
+        // another without ever being inside of all of them
+        let ray = V::pad(&[1.0], 0.0);
+        assert!(get_all_intersections(&world, &V::pad(&[-5.0, 2.0], 0.0), &ray).is_empty());
+        let ray = V::pad(&[1.0, 1.0], 0.0) / Float::sqrt(2.0);
+        assert!(get_all_intersections(&world, &V::pad(&[0.0, -5.0], 0.0), &ray).is_empty());
+
+        // From the inside only the exit is hit
+        let hits = get_all_intersections(&world, &V::new(), &ray);
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].inside);
+        assert!((hits[0].position - V::pad(&[1.0, 1.0], 0.0)).length() < EPSILON);
+    }
+
+    fn polytope_bounds<V: Vector>() {
+        let dimension = V::new().components().len();
+        let mut world = World::new();
+        world.add_polytope(vec![1.0], hypercube_polytope(dimension));
+        world.add_polytope(vec![], hypercube_polytope(dimension - 1));
+        let mut open = Polytope::new(white(), None);
+        open.add_half_space(vec![1.0], 1.0);
+        world.add_polytope(vec![], open);
+        let world = DimensionalWorld::<V>::from_world(&world);
+
+        // All corners of the closed hypercube are inside of its bounds
+        let bounds = world.object_bounds(Object::Polytope(0)).unwrap();
+        for corner in 0..1 << dimension {
+            let corner = V::from_iter((0..dimension).map(|axis| {
+                let sign = if corner & (1 << axis) == 0 { -1.0 } else { 1.0 };
+                if axis == 0 {
+                    1.0 + sign
+                } else {
+                    sign
+                }
+            }));
+
+            for axis in 0..dimension {
+                let c = corner.components()[axis];
+                assert!(bounds.min.components()[axis] <= c + EPSILON);
+                assert!(bounds.max.components()[axis] >= c - EPSILON);
+            }
+        }
+
+        // A lower dimensional polytope is a prism along the missing axis
+        assert!(world.object_bounds(Object::Polytope(1)).is_none());
+        assert!(world.object_bounds(Object::Polytope(2)).is_none());
+    }
+
+    // The boundary of a simplex made up of its facets looks the same as the solid one
+    fn simplex_boundary_matches_solid<V: Vector>() {
+        let dimension = V::new().components().len();
+        let corners: Vec<Vec<Float>> = std::iter::once(vec![0.0; dimension])
+            .chain((0..dimension).map(|axis| {
+                let mut corner = vec![0.0; dimension];
+                corner[axis] = 2.0;
+                corner
+            }))
+            .collect();
+
+        let mut solid = World::new();
+        let mut simplex = Simplex::new(white(), None);
+        for corner in &corners {
+            simplex.add_vertex(corner.clone());
+        }
+        solid.add_simplex(vec![], simplex);
+        let solid = DimensionalWorld::<V>::from_world(&solid);
+
+        // Every facet leaves out one of the corners
+        let mut boundary = World::new();
+        let indices: Vec<u32> = corners
+            .iter()
+            .map(|corner| boundary.add_vertex(corner.clone()))
+            .collect();
+        let mut complex = SimplicialComplex::new(dimension, white(), None);
+        for left_out in 0..indices.len() {
+            let mut facet = indices.clone();
+            facet.remove(left_out);
+            complex.add_facet(facet).unwrap();
+        }
+        boundary.add_complex(vec![], complex).unwrap();
+        let boundary = DimensionalWorld::<V>::from_world(&boundary);
+
+        // Ridge shared by the facet across from the origin and the one in `x1 = 0`, or
+        // a corner in 2D
+        let centroid = V::pad(&[], 2.0 / (dimension + 1) as Float);
+        let ridge = if dimension == 2 {
+            V::pad(&[0.0, 2.0], 0.0)
+        } else {
+            V::from_iter((0..dimension).map(|axis| {
+                if axis == 0 {
+                    0.0
+                } else {
+                    2.0 / (dimension - 1) as Float
+                }
+            }))
+        };
+
+        // Lopsided so they do not pass through ridges by symmetry
+        let skew = V::from_iter((0..dimension).map(|axis| 0.1 * axis as Float));
+        let front = V::pad(&[-3.0], 0.3) + skew;
+        let back = V::pad(&[5.0], -1.0) - skew;
+
+        let rays = [
+            (front, (centroid - front).normalize()),
+            (back, (centroid - back).normalize()),
+            (centroid, V::pad(&[1.0], 0.0)),
+            (ridge * 2.0 - centroid, (centroid - ridge).normalize()),
+        ];
+
+        for (index, (origin, ray)) in rays.iter().enumerate() {
+            let expected = get_all_intersections(&solid, origin, ray);
+            let hits = get_all_intersections(&boundary, origin, ray);
+            assert_eq!(hits.len(), expected.len());
+            assert!(!hits.is_empty());
+
+            for (hit, expected) in hits.iter().zip(&expected) {
+                assert!((hit.distance - expected.distance).abs() < EPSILON);
+                assert_eq!(hit.inside, expected.inside);
+                assert_eq!(hit.normal.dot(ray) > 0.0, hit.inside);
+
+                // The last ray enters through the ridge and leaves through the ridge of
+                // all the facets `xi = 0` but `x1 = 0`, any of their normals is right
+                assert!(index == 3 || hit.normal.dot(&expected.normal) > 0.99);
+            }
+        }
+    }
+
+    // Rays through the tube of every kind of torus, along x through the circle in the xy
+    // plane and from 3D on along z at x = 2. Only the part of the ray inside of the
+    // first tube is found.
+    fn rays_through_hypertorus<V: Vector>() {
+        let dimension = V::new().components().len();
+        let (x, z) = ([1.0], [0.0, 0.0, 1.0]);
+        let tori = [
+            (
+                Hypertorus::spheritorus(2.0, 0.5, white(), None),
+                [(2.5, vec![-1.0]), (3.5, vec![1.0])],
+                [(4.5, vec![0.0, 0.0, -1.0]), (5.5, vec![0.0, 0.0, 1.0])],
+            ),
+            (
+                Hypertorus::torisphere(2.0, 0.5, white(), None),
+                [(2.5, vec![-1.0]), (3.5, vec![1.0])],
+                // The ray grazes along the sphere of radius 2
+                [(3.5, vec![0.8, 0.0, -0.6]), (6.5, vec![0.8, 0.0, 0.6])],
+            ),
+            (
+                Hypertorus::tiger(2.0, 0.3, 0.5, white(), None),
+                [(2.6, vec![-1.0]), (3.4, vec![1.0])],
+                [(4.2, vec![0.0, 0.0, -1.0]), (5.8, vec![0.0, 0.0, 1.0])],
+            ),
+            (
+                Hypertorus::ditorus(2.0, 0.6, 0.2, white(), None),
+                [(2.2, vec![-1.0]), (2.6, vec![1.0])],
+                [(4.2, vec![0.0, 0.0, -1.0]), (4.6, vec![0.0, 0.0, 1.0])],
+            ),
+        ];
+
+        for (torus, along_x, along_z) in tori {
+            let mut world = World::new();
+            world.add_hypertorus(vec![], torus);
+            let world = DimensionalWorld::<V>::from_world(&world);
+
+            let mut rays = vec![(V::pad(&[-5.0], 0.0), V::pad(&x, 0.0), along_x)];
+            if dimension >= 3 {
+                rays.push((V::pad(&[2.0, 0.0, -5.0], 0.0), V::pad(&z, 0.0), along_z));
+            }
+
+            for (origin, ray, expected) in rays {
+                // Sorted from back to front
+                let hits = get_all_intersections(&world, &origin, &ray);
+                assert_eq!(hits.len(), 2);
+
+                for (hit, (distance, normal)) in hits.iter().rev().zip(&expected) {
+                    assert!((hit.distance - distance).abs() < EPSILON);
+                    assert!(hit.normal.dot(&V::pad(normal, 0.0)) > 0.99);
+                }
+            }
+        }
+    }
+
+    // A capsule of radius 1 around the y axis from y = -2 to 2, and one without length
+    // that is just a ball
+    fn rays_through_capsule<V: Vector>() {
+        let capsule = |from: Float, to: Float| {
+            let mut world = World::new();
+            world.add_capsule(
+                vec![0.0, from],
+                vec![0.0, to],
+                Capsule::new(1.0, white(), None),
+            );
+            DimensionalWorld::<V>::from_world(&world)
+        };
+        let (along_x, along_y) = ([1.0, 0.0], [0.0, 1.0]);
+
+        let cases = [
+            // The body and each end cap from the side
+            (
+                capsule(-2.0, 2.0),
+                [-5.0, 0.5],
+                along_x,
+                [(4.0, [-1.0, 0.0]), (6.0, [1.0, 0.0])],
+            ),
+            (
+                capsule(-2.0, 2.0),
+                [-5.0, 2.6],
+                along_x,
+                [(4.2, [-0.8, 0.6]), (5.8, [0.8, 0.6])],
+            ),
+            (
+                capsule(-2.0, 2.0),
+                [-5.0, -2.6],
+                along_x,
+                [(4.2, [-0.8, -0.6]), (5.8, [0.8, -0.6])],
+            ),
+            // Along the axis through both caps
+            (
+                capsule(-2.0, 2.0),
+                [0.0, -5.0],
+                along_y,
+                [(2.0, [0.0, -1.0]), (8.0, [0.0, 1.0])],
+            ),
+            (
+                capsule(0.0, 0.0),
+                [-5.0, 0.6],
+                along_x,
+                [(4.2, [-0.8, 0.6]), (5.8, [0.8, 0.6])],
+            ),
+        ];
+
+        for (world, origin, ray, expected) in &cases {
+            let (origin, ray) = (V::pad(origin, 0.0), V::pad(ray, 0.0));
+
+            // Sorted from back to front
+            let hits = get_all_intersections(world, &origin, &ray);
+            assert_eq!(hits.len(), 2);
+
+            for (hit, (distance, normal)) in hits.iter().rev().zip(expected) {
+                assert!((hit.distance - distance).abs() < EPSILON);
+                assert!(hit.normal.dot(&V::pad(normal, 0.0)) > 0.99);
+            }
+        }
+    }
+
+    // A single ball with a radius of 2 reaches the threshold of 1/8 at a radius of sqrt(2)
+    fn rays_through_metaball<V: Vector>() {
+        let sqrt_2 = std::f64::consts::SQRT_2 as Float;
+        let ray = V::pad(&[1.0], 0.0);
+        let metaballs = |balls: &[(Vec<Float>, Float)]| {
+            let mut metaballs = Metaballs::new(0.125, white(), None);
+            for (center, strength) in balls {
+                metaballs.add_ball(center.clone(), 2.0, *strength);
+            }
+            let mut world = World::new();
+            world.add_metaballs(vec![], metaballs);
+            DimensionalWorld::<V>::from_world(&world)
+        };
+
+        // Through the center, and off center where the normals come from the gradient
+        let world = metaballs(&[(vec![], 1.0)]);
+        let rays = [
+            (
+                0.0,
+                [(5.0 - sqrt_2, [-1.0, 0.0]), (5.0 + sqrt_2, [1.0, 0.0])],
+            ),
+            (1.0, [(4.0, [-1.0, 1.0]), (6.0, [1.0, 1.0])]),
+        ];
+        for (y, expected) in rays {
+            let hits = get_all_intersections(&world, &V::pad(&[-5.0, y], 0.0), &ray);
+            assert_eq!(hits.len(), 2);
+
+            // Sorted from back to front
+            for (hit, (distance, normal)) in hits.iter().rev().zip(&expected) {
+                let normal = V::pad(normal, 0.0).normalize();
+                assert!((hit.distance - distance).abs() < EPSILON);
+                assert!(hit.normal.dot(&normal) > 0.99);
+            }
+        }
+
+        // A ball with a negative strength at x = 2 pulls the exit in from sqrt(2), to
+        // where the field of both adds up to the threshold
+        let world = metaballs(&[(vec![], 1.0), (vec![2.0], -1.0)]);
+        let hits = get_all_intersections(&world, &V::pad(&[-5.0], 0.0), &ray);
+        assert_eq!(hits.len(), 2);
+        assert!((hits[1].distance - (5.0 - sqrt_2)).abs() < EPSILON);
+        assert!((hits[0].distance - 5.925_714).abs() < EPSILON);
+        assert!(hits[0].normal.dot(&ray) > 0.99);
+    }
+
+    // A ball carved out of the middle of a cube leaves two slabs along the ray, the
+    // surfaces of the hole face into it
+    fn ray_through_carved_cube<V: Vector>() {
+        let mut csg = Csg::new();
+        let cube = csg.add_cube(vec![], Cube::new(2.0, white(), None, None));
+        let sphere = csg.add_sphere(vec![], Sphere::new(0.5, white(), None, None));
+        csg.difference(cube, sphere).unwrap();
+
+        let mut world = World::new();
+        world.add_csg(vec![], csg);
+        let world = DimensionalWorld::<V>::from_world(&world);
+
+        let origin = V::pad(&[-5.0], 0.0);
+        let ray = V::pad(&[1.0], 0.0);
+
+        // Sorted from back to front
+        let hits = get_all_intersections(&world, &origin, &ray);
+        assert_eq!(hits.len(), 4);
+
+        for (hit, distance) in hits.iter().zip(&[6.0, 5.5, 4.5, 4.0]) {
+            assert!((hit.distance - distance).abs() < EPSILON);
+            // Sphere normals are only about unit length
+            assert!(hit.normal.dot(&ray).abs() > 0.99);
+        }
+
+        // Leaving the solid into the hole and at the back, entering it at the front and
+        // again behind the hole
+        for (hit, inside) in hits.iter().zip(&[true, false, true, false]) {
+            assert_eq!(hit.inside, *inside);
+            assert_eq!(hit.normal.dot(&ray) > 0.0, *inside);
+        }
+    }
+
+    // The tilted plane z = x1 / 2 + 1, hit from above and from below
+    fn ray_onto_graph<V: Vector>() {
+        let dimension = V::new().components().len();
+        let height_axis = usize::min(2, dimension - 1);
+        let graph = FunctionGraph::new("x1 / 2 + 1", 4.0, white(), None).unwrap();
+
+        let mut world = World::new();
+        world.add_graph(vec![], graph);
+        let world = DimensionalWorld::<V>::from_world(&world);
+
+        let mut up = V::new();
+        up.components_mut()[height_axis] = 1.0;
+        let mut normal = V::pad(&[-0.5], 0.0) + up;
+        normal = normal / normal.length();
+
+        for (height, inside) in [(10.0, false), (-10.0, true)] {
+            let mut origin = V::pad(&[1.0], 0.0);
+            origin.components_mut()[height_axis] = height;
+            let ray = up * -height.signum();
+
+            let hits = get_all_intersections(&world, &origin, &ray);
+            assert_eq!(hits.len(), 1);
+            assert_eq!(hits[0].inside, inside);
+            assert!((hits[0].distance - (height - 1.5).abs()).abs() < EPSILON);
+            assert!((hits[0].position.components()[height_axis] - 1.5).abs() < EPSILON);
+
+            // Always the upwards normal, from below it faces along the ray
+            assert!((hits[0].normal - normal).length() < 1e-2);
+        }
+    }
+
+    // Translucent spheres of a lattice must not hide the ones behind them
+    fn ray_through_lattice<V: Vector>() {
+        let dimension = V::new().components().len();
+        let mut lattice = Lattice::new(10.0).unwrap();
+        for axis in 0..dimension {
+            let mut vector = vec![0.0; dimension];
+            vector[axis] = 2.0;
+            lattice.add_basis_vector(vector);
+        }
+        let color = Color::rgba(1.0, 1.0, 1.0, 0.5);
+        lattice.add_sphere(vec![], Sphere::new(0.5, color, None, None));
+
+        let mut world = World::new();
+        world.add_lattice(vec![], lattice);
+        let world = DimensionalWorld::<V>::from_world(&world);
+
+        let ray = V::pad(&[1.0], 0.0);
+        let hits = get_all_intersections(&world, &V::pad(&[-1.0], 0.0), &ray);
+        assert!(hits.len() >= 8);
+
+        // Sorted from back to front
+        for (hit, distance) in hits.iter().rev().zip(&[0.5, 1.5, 2.5, 3.5]) {
+            assert!((hit.distance - distance).abs() < EPSILON);
+        }
+
+        // Between the rows of spheres nothing is hit up to the max distance
+        let hits = get_all_intersections(&world, &V::pad(&[-1.0, 1.0], 0.0), &ray);
+        assert!(hits.is_empty());
+    }
+
+    // Shadow rays towards a light on the same side of a convex object must not hit it
+    fn no_self_shadowing<V: Vector>(world: &World, center: &V, light: &V) {
+        let world = DimensionalWorld::from_world(world);
+
+        for i in 0..64 {
+            // Rays spread over the lit side of the object
+            let angle = i as Float / 64.0 * std::f64::consts::PI as Float;
+            let mut target = *center;
+            target.components_mut()[0] += angle.cos() * 0.3;
+            target.components_mut()[1] += angle.sin() * 0.3;
+
+            let origin = target + (*light - *center) * 2.0;
+            let ray = target - origin;
+            let ray = ray / ray.length();
+
+            let hits = get_all_intersections(&world, &origin, &ray);
+            let hit = hits
+                .iter()
+                .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap())
+                .expect("camera ray misses the object");
+            assert!(!hit.inside);
+
+            let to_light = *light - hit.position;
+            let to_light = to_light / to_light.length();
+            if hit.normal.dot(&to_light) <= 0.0 {
+                continue;
+            }
+
+            let shadow_origin = offset_origin(hit, &to_light);
+            let shadow_casters = get_all_intersections(&world, &shadow_origin, &to_light);
+            assert!(
+                shadow_casters.is_empty(),
+                "false shadow caster at {} towards {}",
+                hit.position,
+                to_light
+            );
+        }
+    }
+
+    fn shadows_on_primitives<V: Vector>() {
+        let light = V::pad(&[-6.0, -6.0, 12.0, 6.0], 0.0);
+
+        let mut world = World::new();
+        world.add_sphere(vec![50.0], Sphere::new(50.0, white(), None, None));
+        let center = V::pad(&[50.0], 0.0);
+        no_self_shadowing(&world, &center, &(center + light * 10.0));
+
+        let mut world = World::new();
+        world.add_sphere(vec![], Sphere::new(1.5, white(), None, None));
+        no_self_shadowing(&world, &V::new(), &light);
+
+        let mut world = World::new();
+        world.add_cube(vec![], Cube::new(2.0, white(), None, None));
+        no_self_shadowing(&world, &V::new(), &light);
+
+        let mut world = World::new();
+        let mut sdf = Sdf::new(white(), None);
+        let cube = sdf.cube(vec![], 1.6).unwrap();
+        sdf.round(cube, 0.3).unwrap();
+        world.add_sdf(vec![], sdf);
+        no_self_shadowing(&world, &V::new(), &light);
+    }
+
+    // Spheres and boxes keep their properties in parallel arrays, equal surfaces are
+    // stored once
+    fn spheres_and_boxes_per_property<V: Vector>() {
+        let red = Color::rgba(1.0, 0.0, 0.0, 1.0);
+        let mut world = World::new();
+        world.add_sphere(vec![-3.0], Sphere::new(1.0, white(), None, None));
+        world.add_sphere(vec![0.0, 3.0], Sphere::new(0.5, red, None, None));
+        world.add_sphere(vec![3.0], Sphere::new(2.0, white(), None, None));
+        world.add_cube(vec![0.0, -3.0], Cube::new(2.0, white(), None, None));
+        world.add_cube(vec![3.0, -3.0], Cube::new(1.0, white(), None, Some(1.5)));
+        let world = DimensionalWorld::<V>::from_world(&world);
+
+        assert_eq!(world.materials.len(), 3);
+        assert_eq!(world.materials[1].color, red);
+        assert_eq!(world.materials[2].refraction, Some(1.5));
+
+        let spheres = &world.spheres;
+        assert_eq!(spheres.radii, vec![1.0, 0.5, 2.0]);
+        assert_eq!(spheres.materials, vec![0, 1, 0]);
+        assert!((spheres.centers[1] - V::pad(&[0.0, 3.0], 0.0)).length() < EPSILON);
+
+        let aabbs = &world.aabbs;
+        assert_eq!(aabbs.materials, vec![0, 2]);
+        assert!((aabbs.centers[0] - V::pad(&[0.0, -3.0], 0.0)).length() < EPSILON);
+        assert!((aabbs.mins[0] - V::pad(&[-1.0, -4.0], -1.0)).length() < EPSILON);
+        assert!((aabbs.maxs[0] - V::pad(&[1.0, -2.0], 1.0)).length() < EPSILON);
+
+        // Hits refer to the material of the object they are on
+        let hits = get_all_intersections(
+            &world,
+            &V::pad(&[0.0, 8.0], 0.0),
+            &V::pad(&[0.0, -1.0], 0.0),
+        );
+        assert_eq!(hits.len(), 4);
+        assert_eq!(hits[3].material, 1);
+        assert!((hits[3].distance - 4.5).abs() < EPSILON);
+        assert_eq!(hits[0].material, 0);
+        assert!((hits[0].distance - 12.0).abs() < EPSILON);
+    }
+
+    // Packets have to see exactly what single rays see, including translucent objects
+    // and objects that are tested ray by ray
+    fn packet_matches_single_rays<V: Vector + 'static>() {
+        let mut world = World::new();
+        for i in 0..4 {
+            let position = vec![i as Float - 1.5, 0.5 * i as Float, -0.5];
+            let alpha = if i % 2 == 0 { 0.5 } else { 1.0 };
+            let color = Color::rgba(0.2, 0.4, 0.8, alpha);
+            world.add_sphere(position.clone(), Sphere::new(0.6, color, Some(0.3), None));
+            world.add_cube(
+                position,
+                Cube::new(0.8, Color::rgba(0.9, 0.3, 0.1, 0.7), None, None),
+            );
+        }
+        world.add_hypertorus(
+            vec![0.0, -1.5],
+            Hypertorus::spheritorus(1.0, 0.3, white(), None),
+        );
+        world.add_light(vec![-6.0, -6.0, 12.0, 6.0], Light::new(white()));
+
+        for acceleration in [
+            Acceleration::BruteForce,
+            Acceleration::Bvh,
+            Acceleration::Grid,
+        ] {
+            world.set_acceleration(acceleration);
+            let world = DimensionalWorld::<V>::cached(&world);
+            let view = View::new(&world, test_camera(), (0.0, 1.0), (0.0, 1.0));
+
+            for y in 0..16 {
+                for x in (0..16).step_by(PACKET_SIZE) {
+                    let mut points = [(0.0, y as Float / 16.0); PACKET_SIZE];
+                    for (lane, point) in points.iter_mut().enumerate() {
+                        point.0 = (x + lane) as Float / 16.0;
+                    }
+
+                    let colors = sample_packet(&world, &view, points);
+                    for ((rel_x, rel_y), color) in points.iter().zip(&colors) {
+                        assert_eq!(*color, sample(&world, &view, *rel_x, *rel_y));
+                    }
+                }
+            }
+        }
+    }
+
+    fn test_camera<V: Vector>() -> Camera<V> {
+        Camera {
+            position: V::pad(&[4.0, 3.0, 2.0], -8.0),
+            center: V::new(),
+        }
+    }
+
+    // Culling the objects outside of a part of the picture must not change how it looks
+    fn culled_view_matches_full_view<V: Vector + 'static>() {
+        let mut world = World::new();
+        for i in 0..8 {
+            for j in 0..8 {
+                let position = vec![i as Float * 1.5 - 5.0, j as Float * 1.5 - 5.0, 0.0, 0.5];
+                let color = Color::rgba(0.2, 0.4, 0.8, 0.7);
+                // Reflections are traced through the whole world anyway, they'd only
+                // slow the test down
+                world.add_sphere(position, Sphere::new(0.6, color, None, None));
+            }
+        }
+        world.add_light(vec![-6.0, -6.0, 12.0, 6.0], Light::new(white()));
+
+        for acceleration in [
+            Acceleration::BruteForce,
+            Acceleration::Bvh,
+            Acceleration::Grid,
+        ] {
+            world.set_acceleration(acceleration);
+            let world = DimensionalWorld::<V>::cached(&world);
+            let full = View::new(&world, test_camera(), (0.0, 1.0), (0.0, 1.0));
+
+            for part in 0..4 {
+                let (min_y, max_y) = (part as Float / 4.0, (part + 1) as Float / 4.0);
+                let view = View::new(&world, test_camera(), (0.25, 0.75), (min_y, max_y));
+
+                for y in 0..=2 {
+                    for x in 0..=4 {
+                        let rel_x = 0.25 + x as Float / 8.0;
+                        let rel_y = min_y + y as Float / 8.0;
+                        assert_eq!(
+                            sample(&world, &view, rel_x, rel_y),
+                            sample(&world, &full, rel_x, rel_y)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // The accelerators only decide which objects are tested, every one of them has to
+    // find the same hits as testing all objects
+    fn accelerators_find_the_same_hits<V: Vector>() {
+        let dimension = V::new().components().len();
+        let mut world = World::new();
+        for i in 0..5 {
+            for j in 0..5 {
+                let position = vec![i as Float * 1.5 - 3.0, j as Float * 1.5 - 3.0, 0.3];
+                let color = Color::rgba(0.2, 0.4, 0.8, 0.7);
+                if (i + j) % 2 == 0 {
+                    world.add_sphere(position, Sphere::new(0.8, color, None, None));
+                } else {
+                    world.add_cube(position, Cube::new(1.2, color, None, None));
+                }
+            }
+        }
+        world.add_capsule(
+            vec![-4.0, 0.0, -1.0],
+            vec![4.0, 1.0, -1.0],
+            Capsule::new(0.4, white(), None),
+        );
+        world.add_hypertorus(
+            vec![0.0, 0.0, 1.5],
+            Hypertorus::spheritorus(2.0, 0.3, white(), None),
+        );
+        world.add_polytope(vec![1.0, -1.0, 1.0], hypercube_polytope(dimension));
+
+        let origins = [V::pad(&[4.0, 3.0, 2.0], -8.0), V::pad(&[0.5, 0.25], 0.1)];
+        let worlds: Vec<DimensionalWorld<V>> = [
+            Acceleration::BruteForce,
+            Acceleration::Bvh,
+            Acceleration::Grid,
+        ]
+        .iter()
+        .map(|acceleration| {
+            world.set_acceleration(*acceleration);
+            DimensionalWorld::from_world(&world)
+        })
+        .collect();
+
+        for origin in &origins {
+            for x in 0..12 {
+                for y in 0..12 {
+                    let target = V::pad(&[x as Float - 5.5, y as Float - 5.5], 0.0);
+                    let ray = (target - *origin).normalize();
+                    let expected = get_all_intersections(&worlds[0], origin, &ray);
+
+                    for world in &worlds[1..] {
+                        let hits = get_all_intersections(world, origin, &ray);
+                        assert_eq!(hits.len(), expected.len());
+
+                        for (hit, expected) in hits.iter().zip(&expected) {
+                            assert!((hit.distance - expected.distance).abs() < EPSILON);
+                            assert_eq!(hit.material, expected.material);
+                            assert_eq!(hit.inside, expected.inside);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Snell's law in the plane of the ray and the normal, whatever the dimension
+    fn refraction_follows_snells_law<V: Vector>() {
+        let normal = V::pad(&[0.0, 1.0], 0.0);
+        let along = V::pad(&[1.0], 0.0);
+
+        for angle in [0.0, 0.3, 0.6, 0.9, 1.2] {
+            let ray = along * Float::sin(angle) - normal * Float::cos(angle);
+
+            let refracted = refract(&ray, &normal, 1.0 / 1.5).unwrap();
+            assert!((refracted.length() - 1.0).abs() < EPSILON);
+            assert!((refracted.dot(&along) - Float::sin(angle) / 1.5).abs() < EPSILON);
+            assert!(refracted.dot(&normal) < 0.0);
+
+            // Back out of the medium beyond the critical angle of about 0.73
+            let back = refract(&ray, &normal, 1.5);
+            assert_eq!(back.is_none(), angle > 0.73);
+        }
+    }
+
+    fn highlight_peaks_at_mirror_direction<V: Vector>() {
+        let normal = V::pad(&[0.0, 1.0], 0.0);
+        let along = V::pad(&[1.0], 0.0);
+        let to_light = (along + normal).normalize();
+
+        // The mirror direction reflects straight into the light
+        let strength = |angle: Float| {
+            let ray = along * Float::sin(angle) - normal * Float::cos(angle);
+            highlight_strength(&ray, &normal, &to_light, 32.0)
+        };
+        let mirror = std::f64::consts::FRAC_PI_4 as Float;
+        let peak = strength(mirror);
+        assert!(peak > 0.9);
+        for offset in [0.05, 0.2, 0.5] {
+            assert!(strength(mirror - offset) < peak);
+            assert!(strength(mirror + offset) < peak);
+        }
+
+        // Seen from straight above only a dull shine is left
+        assert!(strength(0.0) < 0.2);
+
+        // Light from below, even where the halfway vector would face the normal
+        let ray = normal * -1.0;
+        let below = (along - normal * 0.2).normalize();
+        assert_eq!(highlight_strength(&ray, &normal, &below, 32.0), 0.0);
+        assert_eq!(
+            highlight_strength(&ray, &normal, &(normal * -1.0), 1.0),
+            0.0
+        );
+    }
+
+    // Without lights only the ambient light shows the front, the back is not blended in
+    fn translucent_object_is_one_layer<V: Vector>() {
+        let mut world = World::new();
+        world.add_sphere(
+            vec![],
+            Sphere::new(1.0, Color::rgba(0.0, 0.0, 0.0, 0.6), None, None),
+        );
+        world.add_cube(
+            vec![0.0, 3.0],
+            Cube::new(1.0, Color::rgba(0.0, 0.0, 0.0, 0.6), None, None),
+        );
+        let world = DimensionalWorld::<V>::from_world(&world);
+
+        for origin in [V::pad(&[-5.0], 0.0), V::pad(&[-5.0, 3.0], 0.0)] {
+            let ray = V::pad(&[1.0], 0.0);
+            let color = trace(&world, None, &origin, &ray, REFLECTION_BOUNCES);
+
+            assert_eq!(get_all_intersections(&world, &origin, &ray).len(), 2);
+            assert!((color.red() - BG_COLOR.red() * 0.4).abs() < EPSILON);
+            assert!((color.green() - BG_COLOR.green() * 0.4).abs() < EPSILON);
+            assert!((color.blue() - BG_COLOR.blue() * 0.4).abs() < EPSILON);
+        }
+    }
+
+    // A translucent object lets `1 - alpha` of the light through, not once for its front
+    // and again for its back
+    fn shadow_of_translucent_spheres<V: Vector>() {
+        let red = Color::rgba(1.0, 0.0, 0.0, 0.6);
+        let mut world = World::new();
+        world.add_sphere(vec![], Sphere::new(1.0, red, None, None));
+        let ray = V::pad(&[1.0], 0.0);
+
+        let one = get_light_color(
+            &DimensionalWorld::<V>::from_world(&world),
+            &V::pad(&[-5.0], 0.0),
+            &ray,
+            white(),
+        );
+        assert!((one.red() - 0.4).abs() < EPSILON);
+
+        world.add_sphere(vec![3.0], Sphere::new(1.0, red, None, None));
+        let two = get_light_color(
+            &DimensionalWorld::<V>::from_world(&world),
+            &V::pad(&[-5.0], 0.0),
+            &ray,
+            white(),
+        );
+        assert!((two.red() - 0.16).abs() < EPSILON);
+    }
+
+    // Shadow rays go through the same buffers as other rays for the objects made of
+    // several parts, the light is still dimmed once per entry
+    fn shadow_of_compound_objects<V: Vector>() {
+        let red = Color::rgba(1.0, 0.0, 0.0, 0.6);
+        let dimension = V::new().components().len();
+        let light_color = |world: &World, origin: &V| {
+            let world = DimensionalWorld::<V>::from_world(world);
+            get_light_color(&world, origin, &V::pad(&[1.0], 0.0), white()).red()
+        };
+
+        // The ray enters the cube again behind the hole
+        let mut csg = Csg::new();
+        let cube = csg.add_cube(vec![], Cube::new(2.0, red, None, None));
+        let sphere = csg.add_sphere(vec![], Sphere::new(0.5, red, None, None));
+        csg.difference(cube, sphere).unwrap();
+        let mut world = World::new();
+        world.add_csg(vec![], csg);
+        assert!((light_color(&world, &V::pad(&[-5.0], 0.0)) - 0.16).abs() < EPSILON);
+
+        let mut metaballs = Metaballs::new(0.5, red, None);
+        metaballs.add_ball(vec![], 1.0, 1.0);
+        let mut world = World::new();
+        world.add_metaballs(vec![], metaballs);
+        assert!((light_color(&world, &V::pad(&[-5.0], 0.0)) - 0.4).abs() < EPSILON);
+
+        // Boundary of a simplex, the ray passes through two of its facets
+        let mut world = World::new();
+        let mut indices = vec![world.add_vertex(vec![0.0; dimension])];
+        for axis in 0..dimension {
+            let mut corner = vec![0.0; dimension];
+            corner[axis] = 2.0;
+            indices.push(world.add_vertex(corner));
+        }
+        let mut complex = SimplicialComplex::new(dimension, red, None);
+        for left_out in 0..indices.len() {
+            let mut facet = indices.clone();
+            facet.remove(left_out);
+            complex.add_facet(facet).unwrap();
+        }
+        world.add_complex(vec![], complex).unwrap();
+        let origin = V::pad(&[-5.0], 2.0 / (dimension + 1) as Float);
+        assert!((light_color(&world, &origin) - 0.4).abs() < EPSILON);
+    }
+
+    #[test]
+    fn hit_buffer_keeps_visible_hits() {
+        let materials = [
+            Surface::new(white(), None),
+            Surface::new(Color::rgba(1.0, 1.0, 1.0, 0.5), None),
+        ];
+        let hit = |distance: Float, material| Intersection {
+            position: FixedVector::<3>::pad(&[distance], 0.0),
+            normal: FixedVector::<3>::pad(&[-1.0], 0.0),
+            distance,
+            material,
+            inside: false,
+        };
+        let distances = |hits: &HitBuffer<FixedVector<3>>| {
+            hits.iter().map(|hit| hit.distance).collect::<Vec<_>>()
+        };
+
+        // Nothing behind an opaque hit is visible
+        let mut hits = HitBuffer::new(&materials);
+        for (distance, material) in [(1.0, 1), (3.0, 0), (2.0, 1), (4.0, 1), (5.0, 0)] {
+            hits.push(hit(distance, material));
+        }
+        assert_eq!(distances(&hits), [1.0, 2.0, 3.0]);
+        assert_eq!(hits.max_distance(), 3.0);
+
+        // A full buffer keeps the nearest hits
+        let mut hits = HitBuffer::new(&materials);
+        for distance in (1..=MAX_HITS + 4).rev() {
+            hits.push(hit(distance as Float, 1));
+        }
+        let nearest: Vec<_> = (1..=MAX_HITS).map(|d| d as Float).collect();
+        assert_eq!(distances(&hits), nearest);
+
+        hits.push(hit(0.5, 0));
+        assert_eq!(distances(&hits), [0.5]);
+    }
+
+    #[test]
+    fn sorted_buffer_drops_farthest() {
+        let mut buffer: SortedBuffer<usize, 4> = SortedBuffer::new();
+        for (index, t) in [3.0, 6.0, 1.0, 5.0, 2.0, 4.0].iter().enumerate() {
+            buffer.insert(*t, index);
+        }
+
+        let items: Vec<_> = buffer.iter().collect();
+        assert_eq!(items, [(1.0, 2), (2.0, 4), (3.0, 0), (4.0, 5)]);
+        assert_eq!(buffer.dropped, 2);
+    }
+
+    // Compares tracing opaque scenes with the buffer against collecting and sorting all
+    // hits, run with `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn hit_buffer_speedup() {
+        let mut world = World::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                let position = vec![x as Float * 3.0, y as Float * 3.0 - 10.5, 0.5];
+                world.add_sphere(position, Sphere::new(1.0, white(), None, None));
+            }
+        }
+        let world = DimensionalWorld::<FixedVector<3>>::from_world(&world);
+        let origin = FixedVector::<3>::pad(&[-10.0], 0.0);
+        let rays: Vec<_> = (0..100_000)
+            .map(|i| {
+                let y = (i % 317) as Float / 317.0 - 0.5;
+                let z = (i % 101) as Float / 101.0 - 0.5;
+                FixedVector::<3>::pad(&[1.0, y, z], 0.0).normalize()
+            })
+            .collect();
+
+        let start = std::time::Instant::now();
+        let mut buffered = 0;
+        for ray in &rays {
+            let mut hits = HitBuffer::new(&world.materials);
+            find_intersections(&world, &origin, ray, &mut hits);
+            buffered += hits.iter().count();
+        }
+        let buffer_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let mut all = 0;
+        for ray in &rays {
+            all += get_all_intersections(&world, &origin, ray).len();
+        }
+        let all_time = start.elapsed();
+
+        println!(
+            "buffer: {:?} ({} hits), all hits: {:?} ({} hits), {:.2}x",
+            buffer_time,
+            buffered,
+            all_time,
+            all,
+            all_time.as_secs_f64() / buffer_time.as_secs_f64()
+        );
+    }
+
+    // Instances of a cube with an edge length of 2, hit by rays along x from x = -5.
+    // Distances are measured in the world, not in the space of the prototype.
+    fn rays_through_transformed_instances<V: Vector>() {
+        let angle = std::f64::consts::FRAC_PI_6 as Float;
+        let (sin, cos) = angle.sin_cos();
+        let sqrt_half = std::f64::consts::FRAC_1_SQRT_2 as Float;
+        let transform = |apply: fn(&mut Transform)| {
+            let mut transform = Transform::new();
+            apply(&mut transform);
+            transform
+        };
+
+        // The ray crosses the rotated faces off center, the sheared cube leans towards x
+        // so it only starts at x = -0.5 at y = 0.5
+        let cases = [
+            (
+                transform(|t| t.rotate(0, 1, std::f64::consts::FRAC_PI_6 as Float)),
+                0.0,
+                [
+                    (5.0 - 1.0 / cos, [-cos, -sin]),
+                    (5.0 + 1.0 / cos, [cos, sin]),
+                ],
+            ),
+            (
+                transform(|t| t.scale(2.0)),
+                0.0,
+                [(3.0, [-1.0, 0.0]), (7.0, [1.0, 0.0])],
+            ),
+            (
+                transform(|t| t.scale_axis(0, 0.5)),
+                0.0,
+                [(4.5, [-1.0, 0.0]), (5.5, [1.0, 0.0])],
+            ),
+            (
+                transform(|t| t.shear(0, 1, 1.0)),
+                0.5,
+                [
+                    (4.5, [-sqrt_half, sqrt_half]),
+                    (6.5, [sqrt_half, -sqrt_half]),
+                ],
+            ),
+        ];
+
+        let mut prototype = World::new();
+        prototype.add_cube(vec![], Cube::new(2.0, white(), None, None));
+
+        for (transform, y, expected) in &cases {
+            let mut world = World::new();
+            let index = world.add_prototype(prototype.clone());
+            world.add_instance(index, transform);
+            let world = DimensionalWorld::<V>::from_world(&world);
+
+            let origin = V::pad(&[-5.0, *y], 0.0);
+            let ray = V::pad(&[1.0], 0.0);
+
+            // Sorted from back to front
+            let hits = get_all_intersections(&world, &origin, &ray);
+            assert_eq!(hits.len(), 2);
+
+            for (hit, (distance, normal)) in hits.iter().rev().zip(expected) {
+                assert!((hit.distance - distance).abs() < EPSILON);
+                assert!((hit.position - (origin + ray * *distance)).length() < EPSILON);
+                assert!(hit.normal.dot(&V::pad(normal, 0.0)) > 0.99);
+            }
+        }
+
+        // The surface of the instance replaces the one of the prototype
+        let red = Color::rgba(1.0, 0.0, 0.0, 1.0);
+        let mut world = World::new();
+        let index = world.add_prototype(prototype);
+        world.add_instance(index, &Transform::new());
+        let mut transform = Transform::new();
+        transform.translate(vec![0.0, 5.0]);
+        world.add_instance_with_surface(index, &transform, red, None);
+        let world = DimensionalWorld::<V>::from_world(&world);
+
+        let ray = V::pad(&[1.0], 0.0);
+        for (y, color) in [(0.0, white()), (5.0, red)] {
+            let hits = get_all_intersections(&world, &V::pad(&[-5.0, y], 0.0), &ray);
+            assert_eq!(hits.len(), 2);
+            assert!(hits
+                .iter()
+                .all(|hit| world.materials[hit.material].color == color));
+        }
+    }
+
+    // Every change of the world has to reach the cached worlds of all dimensions
+    fn instances_invalidate_cache<V: Vector + 'static>() {
+        let mut prototype = World::new();
+        prototype.add_sphere(vec![], Sphere::new(1.0, white(), None, None));
+
+        let mut world = World::new();
+        let index = world.add_prototype(prototype);
+        let origin = V::pad(&[-5.0], 0.0);
+        let ray = V::pad(&[1.0], 0.0);
+
+        let cached = DimensionalWorld::<V>::cached(&world);
+        assert!(get_all_intersections(&cached, &origin, &ray).is_empty());
+
+        world.add_instance(index, &Transform::new());
+        let cached = DimensionalWorld::<V>::cached(&world);
+        assert_eq!(get_all_intersections(&cached, &origin, &ray).len(), 2);
+
+        let mut transform = Transform::new();
+        transform.translate(vec![3.0]);
+        world.add_instance_with_surface(index, &transform, white(), None);
+        let cached = DimensionalWorld::<V>::cached(&world);
+        assert_eq!(get_all_intersections(&cached, &origin, &ray).len(), 4);
+    }
+
+    #[test]
+    fn changes_invalidate_cache() {
+        in_all_dimensions!(instances_invalidate_cache);
+    }
+
+    #[test]
+    fn rays_through_instances() {
+        in_all_dimensions!(rays_through_transformed_instances);
+    }
+
+    #[test]
+    fn refraction_in_all_dimensions() {
+        in_all_dimensions!(refraction_follows_snells_law);
+    }
+
+    #[test]
+    fn translucent_objects_are_one_layer() {
+        in_all_dimensions!(translucent_object_is_one_layer);
+    }
+
+    #[test]
+    fn shadows_of_translucent_objects() {
+        in_all_dimensions!(shadow_of_translucent_spheres);
+    }
+
+    #[test]
+    fn shadows_of_compound_objects() {
+        in_all_dimensions!(shadow_of_compound_objects);
+    }
+
+    #[test]
+    fn highlights_in_all_dimensions() {
+        in_all_dimensions!(highlight_peaks_at_mirror_direction);
+    }
+
+    #[test]
+    fn culled_views_match_full_view() {
+        in_all_dimensions!(culled_view_matches_full_view);
+    }
+
+    #[test]
+    fn accelerators_match_brute_force() {
+        in_all_dimensions!(accelerators_find_the_same_hits);
+    }
+
+    #[test]
+    fn materials_are_shared() {
+        in_all_dimensions!(spheres_and_boxes_per_property);
+    }
+
+    #[test]
+    fn packets_match_single_rays() {
+        in_all_dimensions!(packet_matches_single_rays);
+    }
+
+    #[test]
+    fn no_shadow_acne() {
+        in_all_dimensions!(shadows_on_primitives);
+    }
+
+    #[test]
+    fn rays_from_inside_spheres() {
+        in_all_dimensions!(ray_from_inside_sphere);
+    }
+
+    #[test]
+    fn rays_through_spheres() {
+        in_all_dimensions!(ray_through_sphere);
+    }
+
+    #[test]
+    fn rays_through_cones() {
+        in_all_dimensions!(ray_through_cone);
+    }
+
+    #[test]
+    fn rays_along_cone_generators() {
+        in_all_dimensions!(ray_along_cone_generator);
+    }
+
+    #[test]
+    fn complexes_match_simplices() {
+        in_all_dimensions!(simplex_boundary_matches_solid);
+    }
+
+    #[test]
+    fn rays_through_polytopes() {
+        in_all_dimensions!(ray_through_polytope);
+    }
+
+    #[test]
+    fn bounds_of_polytopes() {
+        in_all_dimensions!(polytope_bounds);
+    }
+
+    #[test]
+    fn rays_through_hypertori() {
+        in_all_dimensions!(rays_through_hypertorus);
+    }
+
+    #[test]
+    fn rays_through_capsules() {
+        in_all_dimensions!(rays_through_capsule);
+    }
+
+    #[test]
+    fn rays_through_metaballs() {
+        in_all_dimensions!(rays_through_metaball);
+    }
+
+    #[test]
+    fn rays_through_carved_cubes() {
+        in_all_dimensions!(ray_through_carved_cube);
+    }
+
+    #[test]
+    fn rays_onto_graphs() {
+        in_all_dimensions!(ray_onto_graph);
+    }
+
+    #[test]
+    fn rays_through_lattices() {
+        in_all_dimensions!(ray_through_lattice);
+    }
+
+    #[test]
+    fn rays_from_inside_cubes() {
+        in_all_dimensions!(ray_from_inside_cube);
+    }
+
+    #[test]
+    fn rays_through_cubes() {
+        in_all_dimensions!(ray_through_cube);
+    }
 }
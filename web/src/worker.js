@@ -56,6 +56,18 @@ function packSpheres3(world) {
 function packSpheres4(world) {
   let colorN = 0;
   const r = Math.hypot(0.5, 0.5, 0.5, 0.5) / 2;
+
+  const prototype = new lib.World();
+  prototype.add_sphere([], new lib.Sphere(r, lib.Color.rgba(1, 1, 1, 1)));
+  const sphere = world.add_prototype(prototype);
+
+  const place = (pos, color) => {
+    const transform = new lib.Transform();
+    transform.translate(pos);
+    world.add_instance_with_surface(sphere, transform, color);
+    transform.free();
+  };
+
   for (var x = -1; x <= 1; x++) {
     for (var y = -1; y <= 1; y++) {
       for (var z = -1; z <= 1; z++) {
@@ -67,13 +79,10 @@ function packSpheres4(world) {
             .split("")
             .map(Number)
             .map(n => n * 0.6 + 0.2);
-          world.add_sphere(
-            [x, y, z, w],
-            new lib.Sphere(r, lib.Color.rgba(...color, 0, 0.5))
-          );
-          world.add_sphere(
+          place([x, y, z, w], lib.Color.rgba(...color, 0, 0.5));
+          place(
             [x + 1 / 2, y + 1 / 2, z + 1 / 2, w + 1 / 2],
-            new lib.Sphere(r, lib.Color.rgba(...color, 1, 0.5))
+            lib.Color.rgba(...color, 1, 0.5)
           );
         }
       }
@@ -114,41 +123,50 @@ function stackSpheres(world, dimension) {
   world.add_sphere([], new lib.Sphere(innerR, hexColor("#d200f9"), 0.4));
 }
 
-function cube(world, dimension, pos, orgDimension = dimension) {
-  let count = 4;
-  let scale = 2;
-  let radius = 0.4;
-  let outline = true;
-
-  if (dimension === 0) {
-    const axies = pos.filter(c => Math.abs(c) === scale / 2).length;
+// Spheres on the vertices and capsules along the edges of a regular polytope
+function wireframe(world, polytope, { radius, sphereRadius, edgeRadius, reflection }) {
+  const dimension = polytope.dimension();
+  const vertices = [];
+  for (let i = 0; i < polytope.vertex_count(); i++) {
+    vertices.push(polytope.vertex(i, radius));
+  }
 
-    if (outline && axies < 2) {
-      return;
-    }
+  // Color by the number of components that are on the outside, like the corners
+  const extent = Math.max(...vertices[0].map(Math.abs));
+  const colorAt = pos => {
+    const axies = pos.filter(c => Math.abs(Math.abs(c) - extent) < 1e-4).length;
 
-    world.add_sphere(
-      pos,
-      new lib.Sphere(
-        radius,
-        lib.Color.rgba(
-          axies / Math.max(1, orgDimension - 2),
-          (orgDimension - axies) / Math.max(1, orgDimension - 1),
-          (orgDimension - axies) / Math.max(1, orgDimension - 1),
-          1
-        )
-      )
+    return lib.Color.rgba(
+      axies / Math.max(1, dimension),
+      (dimension - axies) / Math.max(1, dimension - 1),
+      (dimension - axies) / Math.max(1, dimension - 1),
+      1
+    );
+  };
+
+  vertices.forEach(pos => {
+    world.add_sphere(pos, new lib.Sphere(sphereRadius, colorAt(pos), reflection));
+  });
+
+  const edges = polytope.edges();
+  for (let e = 0; e < edges.length; e += 2) {
+    const from = vertices[edges[e]];
+    const to = vertices[edges[e + 1]];
+    const middle = from.map((c, axis) => (c + to[axis]) / 2);
+    world.add_capsule(
+      from,
+      to,
+      new lib.Capsule(edgeRadius, colorAt(middle), reflection)
     );
-
-    return;
   }
+}
 
-  for (var i = 0; i < count; i++) {
-    // recursively go through dimensions
-    // component is fitst x, than y, than z, etc
-    const component = (i / (count - 1)) * scale - scale / 2;
-    cube(world, dimension - 1, [component, ...pos], orgDimension);
-  }
+function cube(world, dimension) {
+  wireframe(world, lib.RegularPolytope.hypercube(dimension), {
+    radius: Math.sqrt(dimension),
+    sphereRadius: 0.4,
+    edgeRadius: 0.15
+  });
 }
 
 function colorCube(world, dimension) {
@@ -210,56 +228,13 @@ function colorCube(world, dimension) {
   }
 }
 
-function box(world, dimension, pos, orgDimension = dimension) {
-  let count = 6;
-  let scale = 22;
-  let radius = 2;
-
-  if (dimension === 0) {
-    const axies = pos.filter(c => Math.abs(c) === scale / 2).length;
-
-    // Only wireframe
-    if (axies < 2) {
-      return;
-    }
-
-    // Only one half
-    // if (pos.some(n => n === scale / 2)) {
-    //   return;
-    // }
-
-    world.add_sphere(
-      pos,
-      new lib.Sphere(
-        radius,
-        lib.Color.rgba(
-          axies / (orgDimension - 2),
-          (orgDimension - axies) / (orgDimension - 1),
-          (orgDimension - axies) / (orgDimension - 1),
-          1
-        ),
-        0.7
-      )
-      // new lib.Sphere(
-      //   radius,
-      //   lib.Color.rgba(
-      //     axies / (orgDimension - 2),
-      //     (orgDimension - axies) / (orgDimension - 1),
-      //     (orgDimension - axies) / (orgDimension - 1),
-      //     0.8
-      //   )
-      // )
-    );
-
-    return;
-  }
-
-  for (var i = 0; i < count; i++) {
-    // recursively go through dimensions
-    // component is fitst x, than y, than z, etc
-    const component = (i / (count - 1)) * scale - scale / 2;
-    box(world, dimension - 1, [component, ...pos], orgDimension);
-  }
+function box(world, dimension) {
+  wireframe(world, lib.RegularPolytope.hypercube(dimension), {
+    radius: 11 * Math.sqrt(dimension),
+    sphereRadius: 2,
+    edgeRadius: 0.8,
+    reflection: 0.7
+  });
 }
 
 function ncubes(world, dimension) {
@@ -281,6 +256,214 @@ function ncubes(world, dimension) {
   );
 }
 
+function lightCone(world) {
+  // The z axis is time, future and past light cone of an event at the origin
+  world.add_cone(
+    [0, 0, 0],
+    new lib.Cone([0, 0, 1], Math.PI / 4, 2.5, true, hexColor("#ffec5c", 0.6))
+  );
+  world.add_cone(
+    [0, 0, 0],
+    new lib.Cone([0, 0, -1], Math.PI / 4, 2.5, true, hexColor("#034df1", 0.6))
+  );
+  world.add_sphere([], new lib.Sphere(0.15, hexColor("#d53f47")));
+}
+
+function simplices(world, dimension) {
+  const scale = 2.5;
+  const corners = [new Array(dimension).fill(0)];
+  for (let i = 0; i < dimension; i++) {
+    const corner = new Array(dimension).fill(0);
+    corner[i] = scale;
+    corners.push(corner);
+  }
+
+  // Solid simplex
+  const simplex = new lib.Simplex(hexColor("#30e42d", 0.8), 0.2);
+  corners.forEach(corner => simplex.add_vertex(corner));
+  world.add_simplex(new Array(dimension).fill(-scale), simplex);
+
+  // The same simplex as the boundary made up of facets, i.e. every facet leaves out one corner
+  const complex = new lib.SimplicialComplex(dimension, hexColor("#d53f47", 0.8), 0.2);
+  const indices = corners.map(corner => world.add_vertex(corner));
+  for (let skip = 0; skip < indices.length; skip++) {
+    complex.add_facet(new Uint32Array(indices.filter((_, i) => i !== skip)));
+  }
+  world.add_complex([], complex);
+}
+
+function regularPolytope(world, polytope) {
+  world.add_polytope(
+    [],
+    polytope.to_polytope(1.5, hexColor("#d200f9", 0.9), 0.3)
+  );
+}
+
+function hypertori(world) {
+  world.add_hypertorus(
+    [-1.8, -1.8],
+    lib.Hypertorus.spheritorus(1, 0.4, hexColor("#034df1"), 0.2)
+  );
+  world.add_hypertorus(
+    [1.8, -1.8],
+    lib.Hypertorus.torisphere(1, 0.3, hexColor("#30e42d"), 0.2)
+  );
+  world.add_hypertorus(
+    [-1.8, 1.8],
+    lib.Hypertorus.tiger(0.9, 0.9, 0.35, hexColor("#d53f47"), 0.2)
+  );
+  world.add_hypertorus(
+    [1.8, 1.8],
+    lib.Hypertorus.ditorus(1, 0.45, 0.2, hexColor("#ffec5c"), 0.2)
+  );
+}
+
+function distanceFields(world) {
+  // Rounded hyper-cube blended with a hyper-sphere
+  const blob = new lib.Sdf(hexColor("#30e42d"), 0.2);
+  blob.smooth_union(
+    blob.round(blob.cube([], 1.2), 0.2),
+    blob.sphere([0.8, 0.8, 0.8, 0.8], 0.6),
+    0.5
+  );
+  world.add_sdf([-1.6, 0], blob);
+
+  // Twisted bar
+  const bar = new lib.Sdf(hexColor("#d53f47"), 0.2);
+  let shape = bar.cube([], 2.4);
+  shape = bar.intersection(shape, bar.plane([1], 0.25));
+  shape = bar.intersection(shape, bar.plane([-1], 0.25));
+  shape = bar.intersection(shape, bar.plane([0, 1], 0.6));
+  shape = bar.intersection(shape, bar.plane([0, -1], 0.6));
+  bar.twist(shape, 1.2);
+  world.add_sdf([1.6, 0], bar);
+}
+
+function fractals(world) {
+  const sponge = new lib.Sdf(hexColor("#034df1"), 0.2);
+  sponge.menger([], 2.4, 3);
+  world.add_sdf([-1.6, 0], sponge);
+
+  const gasket = new lib.Sdf(hexColor("#ffec5c"), 0.2);
+  gasket.sierpinski([], 1.6, 4);
+  world.add_sdf([1.6, 0], gasket);
+}
+
+function functionGraph(world) {
+  // A ripple spreading out over x, y and w
+  const graph = new lib.FunctionGraph(
+    "cos(3 * sqrt(x1^2 + x2^2 + x3^2)) * exp(-(x1^2 + x2^2 + x3^2) / 4)",
+    2.5,
+    hexColor("#034df1"),
+    0.2
+  );
+  world.add_graph([], graph);
+}
+
+function carvedCube(world) {
+  // A tesseract with a hyper-sphere carved out of it
+  const csg = new lib.Csg();
+  csg.difference(
+    csg.add_cube([], new lib.Cube(2.4, hexColor("#034df1"), 0.2)),
+    csg.add_sphere([], new lib.Sphere(1.55, hexColor("#ffec5c")))
+  );
+  world.add_csg([], csg);
+}
+
+function metaballs(world, dimension) {
+  // Two balls on every axis, blended into a single blob
+  const blobs = new lib.Metaballs(0.4, hexColor("#30e42d"), 0.2);
+  for (let axis = 0; axis < dimension; axis++) {
+    const center = new Array(dimension).fill(0);
+    center[axis] = 1.2;
+    blobs.add_ball(center, 2, 1);
+    center[axis] = -1.2;
+    blobs.add_ball(center, 2, 1);
+  }
+  world.add_metaballs([], blobs);
+}
+
+function grazingLight(world) {
+  // A huge reflective ground sphere lit almost along its surface, where rays
+  // starting on a surface used to hit it again and speckle it with shadow acne
+  world.add_sphere(
+    [0, 0, -43.6],
+    new lib.Sphere(40, lib.Color.rgba(0.9, 0.9, 0.9, 1), 0.4)
+  );
+  world.add_sphere([-1.6, 0], new lib.Sphere(1, hexColor("#d53f47"), 0.3));
+  world.add_cube([1.6, 0], new lib.Cube(1.6, hexColor("#034df1"), 0.3));
+  world.add_hypertorus(
+    [0, 2.4],
+    lib.Hypertorus.spheritorus(0.9, 0.35, hexColor("#30e42d"), 0.3)
+  );
+
+  world.add_light(
+    [-30.0, 4.0, 0.5, 0.0],
+    new lib.Light(lib.Color.rgba(1, 0.9, 0.7, 0.4))
+  );
+}
+
+function glass(world, dimension) {
+  // A glass hypersphere in front of colored spheres, bending the view of all axes
+  world.add_sphere(
+    [0, 0, 0.5],
+    new lib.Sphere(1.6, lib.Color.rgba(0.9, 0.95, 1, 0.05), 0.1, 1.5)
+  );
+  world.add_cube(
+    [2.6, -2.6, 0.5],
+    new lib.Cube(1.6, lib.Color.rgba(0.8, 1, 0.9, 0.1), 0, 1.3)
+  );
+
+  const colors = ["#d53f47", "#034df1", "#30e42d", "#ffec5c", "#d200f9"];
+  for (let i = 0; i < 5; i++) {
+    for (let d = 0; d < dimension; d++) {
+      const pos = new Array(dimension).fill(0);
+      pos[d] = (i % 2 === 0 ? -1 : 1) * (2.5 + i * 0.6);
+      world.add_sphere(pos, new lib.Sphere(0.5, hexColor(colors[i])));
+    }
+  }
+}
+
+// Basis of the checkerboard lattice D_n, i.e. integer points with an even sum
+function checkerboardBasis(dimension) {
+  const basis = [];
+  for (let i = 0; i < dimension; i++) {
+    const vector = new Array(dimension).fill(0);
+    if (i === 0) {
+      vector[0] = -1;
+      vector[1] = -1;
+    } else {
+      vector[i - 1] = 1;
+      vector[i] = -1;
+    }
+    basis.push(vector);
+  }
+  return basis;
+}
+
+function latticeD(world, dimension) {
+  const lattice = new lib.Lattice(40);
+  checkerboardBasis(dimension).forEach(v => lattice.add_basis_vector(v));
+  lattice.add_sphere([], new lib.Sphere(0.25, hexColor("#034df1"), 0.3));
+  world.add_lattice([], lattice);
+}
+
+function latticeE8(world, dimension) {
+  if (dimension !== 8) {
+    return latticeD(world, dimension);
+  }
+
+  // E8 is D8 together with a copy of it shifted by (1/2, ..., 1/2)
+  const lattice = new lib.Lattice(20);
+  checkerboardBasis(8).forEach(v => lattice.add_basis_vector(v));
+  lattice.add_sphere([], new lib.Sphere(0.25, hexColor("#034df1"), 0.3));
+  lattice.add_sphere(
+    new Array(8).fill(0.5),
+    new lib.Sphere(0.25, hexColor("#d53f47"), 0.3)
+  );
+  world.add_lattice([], lattice);
+}
+
 function update({ data, camPos, start, end, width, height, dimension }) {
   return lib.update(
     data,
@@ -295,10 +478,8 @@ function update({ data, camPos, start, end, width, height, dimension }) {
   );
 }
 
-async function start({ dimension, scene }) {
-  lib = await libPromise;
-
-  world = new lib.World();
+function buildWorld(dimension, scene) {
+  const world = new lib.World();
 
   switch (scene) {
     case "spheres-on-cube":
@@ -308,11 +489,59 @@ async function start({ dimension, scene }) {
       colorCube(world, dimension);
       break;
     case "cube":
-      cube(world, dimension, [], dimension);
+      cube(world, dimension);
       break;
     case "n-cubes":
       ncubes(world, dimension);
       break;
+    case "light-cone":
+      lightCone(world);
+      break;
+    case "simplex":
+      simplices(world, dimension);
+      break;
+    case "24-cell":
+      regularPolytope(world, lib.RegularPolytope.cell_24());
+      break;
+    case "120-cell":
+      regularPolytope(world, lib.RegularPolytope.cell_120());
+      break;
+    case "600-cell":
+      regularPolytope(world, lib.RegularPolytope.cell_600());
+      break;
+    case "cross-polytope":
+      regularPolytope(world, lib.RegularPolytope.cross_polytope(dimension));
+      break;
+    case "hypertori":
+      hypertori(world);
+      break;
+    case "distance-fields":
+      distanceFields(world);
+      break;
+    case "fractals":
+      fractals(world);
+      break;
+    case "function-graph":
+      functionGraph(world);
+      break;
+    case "carved-cube":
+      carvedCube(world);
+      break;
+    case "metaballs":
+      metaballs(world, dimension);
+      break;
+    case "grazing-light":
+      grazingLight(world);
+      break;
+    case "glass":
+      glass(world, dimension);
+      break;
+    case "lattice-d":
+      latticeD(world, dimension);
+      break;
+    case "lattice-e8":
+      latticeE8(world, dimension);
+      break;
     case "sphere-packing-2":
       packSpheres2(world);
       break;
@@ -351,7 +580,7 @@ async function start({ dimension, scene }) {
     }
   }
 
-  // box(world, dimension, [], dimension);
+  // box(world, dimension);
 
   // world.add_sphere(
   //   [0, 3.5, 0],
@@ -369,6 +598,60 @@ async function start({ dimension, scene }) {
   //   [-3.5, 0, 0],
   //   new lib.Sphere(0.5, lib.Color.rgba(1, 0.1, 0, 1), 0.2)
   // );
+
+  return world;
+}
+
+async function start({ dimension, scene }) {
+  lib = await libPromise;
+
+  world = buildWorld(dimension, scene);
+}
+
+// Renders every scene once per acceleration structure, in milliseconds per frame. Packets
+// are only traced for the grid of samples a frame starts with, so that is timed on its
+// own with single rays and with packets.
+async function benchmark({ dimension, scenes, camPos, size }) {
+  lib = await libPromise;
+
+  const accelerations = {
+    "brute force": lib.Acceleration.BruteForce,
+    bvh: lib.Acceleration.Bvh,
+    grid: lib.Acceleration.Grid
+  };
+  const passes = [
+    ["", lib.update, true],
+    [" grid", lib.update_sample_grid, false],
+    [" grid (packets)", lib.update_sample_grid, true]
+  ];
+  const results = [];
+
+  for (const scene of scenes) {
+    const result = { scene };
+
+    for (const [name, acceleration] of Object.entries(accelerations)) {
+      for (const [pass, render, packets] of passes) {
+        const world = buildWorld(dimension, scene);
+        world.set_acceleration(acceleration);
+        world.set_packet_tracing(packets);
+
+        const data = new Uint8ClampedArray(size * size * 4);
+        // The first frame also converts the world to the dimension and builds the
+        // acceleration structure, which later frames take from the cache
+        render(data, world, camPos, 0, size, size, size, size, dimension);
+
+        const begin = performance.now();
+        render(data, world, camPos, 0, size, size, size, size, dimension);
+        result[name + pass] = Math.round(performance.now() - begin);
+
+        world.free();
+      }
+    }
+
+    results.push(result);
+  }
+
+  return results;
 }
 
 function handleEvent(type, data) {
@@ -377,6 +660,8 @@ function handleEvent(type, data) {
       return start(data);
     case "update":
       return update(data);
+    case "benchmark":
+      return benchmark(data);
   }
 }
 
@@ -1,5 +1,10 @@
 use crate::color::Color;
+use crate::expr::Expr;
 use ndrt_lib::Float;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 use wasm_bindgen::prelude::*;
 
@@ -8,6 +13,23 @@ use wasm_bindgen::prelude::*;
 pub struct Surface {
     pub(crate) color: Color,
     pub(crate) reflection: Float,
+    // Index of refraction of the inside, `None` lets what shines through pass straight on
+    pub(crate) refraction: Option<Float>,
+    // Color of highlights with their strength as alpha, they get smaller with `shininess`
+    pub(crate) specular: Color,
+    pub(crate) shininess: Float,
+}
+
+impl Surface {
+    pub(crate) fn new(color: Color, reflection: Option<Float>) -> Self {
+        Self {
+            color,
+            reflection: reflection.unwrap_or(0.0),
+            refraction: None,
+            specular: Color::rgba(1.0, 1.0, 1.0, 0.3),
+            shininess: 32.0,
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -33,16 +55,31 @@ pub struct Sphere {
 
 #[wasm_bindgen]
 impl Sphere {
+    /// With `refraction` the translucent part of `color` is bent like glass,
+    /// it is the index of refraction of the inside
     #[wasm_bindgen(constructor)]
-    pub fn new(radius: Float, color: Color, reflection: Option<Float>) -> Self {
+    pub fn new(
+        radius: Float,
+        color: Color,
+        reflection: Option<Float>,
+        refraction: Option<Float>,
+    ) -> Self {
         Self {
             radius,
             surface: Surface {
-                color,
-                reflection: reflection.unwrap_or(0.0),
+                refraction,
+                ..Surface::new(color, reflection)
             },
         }
     }
+
+    /// Highlights are white with a strength of 0.3 and a shininess of 32 unless set,
+    /// the alpha of `color` is their strength
+    #[wasm_bindgen]
+    pub fn set_specular(&mut self, color: Color, shininess: Float) {
+        self.surface.specular = color;
+        self.surface.shininess = shininess;
+    }
 }
 
 #[wasm_bindgen]
@@ -54,16 +91,744 @@ pub struct Cube {
 
 #[wasm_bindgen]
 impl Cube {
+    /// With `refraction` the translucent part of `color` is bent like glass,
+    /// it is the index of refraction of the inside
     #[wasm_bindgen(constructor)]
-    pub fn new(size: Float, color: Color, reflection: Option<Float>) -> Self {
+    pub fn new(
+        size: Float,
+        color: Color,
+        reflection: Option<Float>,
+        refraction: Option<Float>,
+    ) -> Self {
         Self {
             size,
             surface: Surface {
-                color,
-                reflection: reflection.unwrap_or(0.0),
+                refraction,
+                ..Surface::new(color, reflection)
             },
         }
     }
+
+    /// Highlights are white with a strength of 0.3 and a shininess of 32 unless set,
+    /// the alpha of `color` is their strength
+    #[wasm_bindgen]
+    pub fn set_specular(&mut self, color: Color, shininess: Float) {
+        self.surface.specular = color;
+        self.surface.shininess = shininess;
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Cone {
+    pub(crate) axis: Vec<Float>,
+    pub(crate) angle: Float,
+    pub(crate) height: Float,
+    pub(crate) capped: bool,
+    pub(crate) surface: Surface,
+}
+
+#[wasm_bindgen]
+impl Cone {
+    /// A cone with its apex at the position it is added at, opening along
+    /// `axis` with the half-angle `angle` (in radians) up to `height`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        axis: Vec<Float>,
+        angle: Float,
+        height: Float,
+        capped: bool,
+        color: Color,
+        reflection: Option<Float>,
+    ) -> Self {
+        Self {
+            axis,
+            angle,
+            height,
+            capped,
+            surface: Surface::new(color, reflection),
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Capsule {
+    pub(crate) radius: Float,
+    pub(crate) surface: Surface,
+}
+
+#[wasm_bindgen]
+impl Capsule {
+    /// All points within `radius` of the line segment it is added with
+    #[wasm_bindgen(constructor)]
+    pub fn new(radius: Float, color: Color, reflection: Option<Float>) -> Self {
+        Self {
+            radius,
+            surface: Surface::new(color, reflection),
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Simplex {
+    pub(crate) vertices: Vec<Vec<Float>>,
+    pub(crate) surface: Surface,
+}
+
+#[wasm_bindgen]
+impl Simplex {
+    /// An N-simplex needs exactly N + 1 vertices, it is not rendered in other dimensions
+    #[wasm_bindgen(constructor)]
+    pub fn new(color: Color, reflection: Option<Float>) -> Self {
+        Self {
+            vertices: vec![],
+            surface: Surface::new(color, reflection),
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn add_vertex(&mut self, pos: Vec<Float>) {
+        self.vertices.push(pos);
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct SimplicialComplex {
+    pub(crate) dimension: usize,
+    // Indices into the vertices of the `World`, `dimension` per facet
+    pub(crate) facets: Vec<u32>,
+    pub(crate) surface: Surface,
+}
+
+#[wasm_bindgen]
+impl SimplicialComplex {
+    /// The boundary of an N-dimensional object made up of (N-1)-simplices,
+    /// i.e. every facet has N vertices
+    #[wasm_bindgen(constructor)]
+    pub fn new(dimension: usize, color: Color, reflection: Option<Float>) -> Self {
+        Self {
+            dimension,
+            facets: vec![],
+            surface: Surface::new(color, reflection),
+        }
+    }
+
+    /// Fails unless the facet has exactly `dimension` vertices
+    #[wasm_bindgen]
+    pub fn add_facet(&mut self, vertices: Vec<u32>) -> Result<(), JsValue> {
+        if vertices.len() != self.dimension {
+            return Err(JsValue::from_str(&format!(
+                "a facet needs {} vertices, got {}",
+                self.dimension,
+                vertices.len()
+            )));
+        }
+
+        self.facets.extend(vertices);
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Polytope {
+    pub(crate) half_spaces: Vec<(Vec<Float>, Float)>,
+    // Radius of a ball around the position that contains the polytope, if it is known
+    // to be closed
+    pub(crate) bounds_radius: Option<Float>,
+    pub(crate) surface: Surface,
+}
+
+#[wasm_bindgen]
+impl Polytope {
+    /// A convex polytope, i.e. the intersection of half-spaces
+    #[wasm_bindgen(constructor)]
+    pub fn new(color: Color, reflection: Option<Float>) -> Self {
+        Self {
+            half_spaces: vec![],
+            bounds_radius: None,
+            surface: Surface::new(color, reflection),
+        }
+    }
+
+    /// Limits the polytope to all points `p` where `normal . p <= offset`
+    #[wasm_bindgen]
+    pub fn add_half_space(&mut self, normal: Vec<Float>, offset: Float) {
+        self.half_spaces.push((normal, offset));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TorusKind {
+    // Circle swept by a sphere
+    Spheritorus,
+    // Sphere swept by a circle
+    Torisphere,
+    // Two circles in orthogonal planes swept by a circle
+    Tiger,
+    // Circle swept by a circle swept by a circle
+    Ditorus,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Hypertorus {
+    pub(crate) kind: TorusKind,
+    // Radii from the outermost to the innermost (the tube)
+    pub(crate) radii: [Float; 3],
+    pub(crate) surface: Surface,
+}
+
+#[wasm_bindgen]
+impl Hypertorus {
+    fn with_kind(
+        kind: TorusKind,
+        radii: [Float; 3],
+        color: Color,
+        reflection: Option<Float>,
+    ) -> Self {
+        Self {
+            kind,
+            radii,
+            surface: Surface::new(color, reflection),
+        }
+    }
+
+    /// A circle of radius `major` in the xy plane swept by a sphere of radius `minor`
+    pub fn spheritorus(
+        major: Float,
+        minor: Float,
+        color: Color,
+        reflection: Option<Float>,
+    ) -> Self {
+        Self::with_kind(
+            TorusKind::Spheritorus,
+            [major, 0.0, minor],
+            color,
+            reflection,
+        )
+    }
+
+    /// A sphere of radius `major` in xyz swept by a circle of radius `minor`
+    pub fn torisphere(major: Float, minor: Float, color: Color, reflection: Option<Float>) -> Self {
+        Self::with_kind(
+            TorusKind::Torisphere,
+            [major, 0.0, minor],
+            color,
+            reflection,
+        )
+    }
+
+    /// The circles of radius `major_xy` in the xy plane and `major_zw` in the
+    /// zw plane (i.e. a clifford torus) swept by a circle of radius `minor`
+    pub fn tiger(
+        major_xy: Float,
+        major_zw: Float,
+        minor: Float,
+        color: Color,
+        reflection: Option<Float>,
+    ) -> Self {
+        Self::with_kind(
+            TorusKind::Tiger,
+            [major_xy, major_zw, minor],
+            color,
+            reflection,
+        )
+    }
+
+    /// A torus with the radii `major` and `middle` swept by a circle of radius `minor`
+    pub fn ditorus(
+        major: Float,
+        middle: Float,
+        minor: Float,
+        color: Color,
+        reflection: Option<Float>,
+    ) -> Self {
+        Self::with_kind(
+            TorusKind::Ditorus,
+            [major, middle, minor],
+            color,
+            reflection,
+        )
+    }
+}
+
+// Node of a signed distance field, operators reference the nodes added before them
+#[derive(Debug, Clone)]
+pub(crate) enum SdfNode {
+    Sphere {
+        center: Vec<Float>,
+        radius: Float,
+    },
+    Cube {
+        center: Vec<Float>,
+        size: Float,
+    },
+    Capsule {
+        from: Vec<Float>,
+        to: Vec<Float>,
+        radius: Float,
+    },
+    Plane {
+        normal: Vec<Float>,
+        offset: Float,
+    },
+    Menger {
+        center: Vec<Float>,
+        size: Float,
+        depth: u32,
+    },
+    Sierpinski {
+        center: Vec<Float>,
+        radius: Float,
+        depth: u32,
+    },
+    Union(u32, u32),
+    Intersection(u32, u32),
+    Difference(u32, u32),
+    SmoothUnion(u32, u32, Float),
+    Round(u32, Float),
+    Twist(u32, Float),
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Sdf {
+    // The last node is the root
+    pub(crate) nodes: Vec<SdfNode>,
+    pub(crate) surface: Surface,
+}
+
+#[wasm_bindgen]
+impl Sdf {
+    /// A shape described by a signed distance field, it is build up from nodes.
+    /// Every method adds a node and returns its id to be used by the operators,
+    /// which fail unless they refer to nodes added before them.
+    #[wasm_bindgen(constructor)]
+    pub fn new(color: Color, reflection: Option<Float>) -> Self {
+        Self {
+            nodes: vec![],
+            surface: Surface::new(color, reflection),
+        }
+    }
+
+    // Operators can only refer to earlier nodes, so the tree has no cycles
+    fn add(&mut self, node: SdfNode) -> Result<u32, JsValue> {
+        let id = self.nodes.len() as u32;
+        let operands = match node {
+            SdfNode::Union(a, b)
+            | SdfNode::Intersection(a, b)
+            | SdfNode::Difference(a, b)
+            | SdfNode::SmoothUnion(a, b, _) => vec![a, b],
+            SdfNode::Round(a, _) | SdfNode::Twist(a, _) => vec![a],
+            _ => vec![],
+        };
+
+        if let Some(operand) = operands.iter().find(|operand| **operand >= id) {
+            return Err(JsValue::from_str(&format!(
+                "node {} can only refer to earlier nodes, not {}",
+                id, operand
+            )));
+        }
+
+        self.nodes.push(node);
+        Ok(id)
+    }
+
+    pub fn sphere(&mut self, center: Vec<Float>, radius: Float) -> Result<u32, JsValue> {
+        self.add(SdfNode::Sphere { center, radius })
+    }
+
+    pub fn cube(&mut self, center: Vec<Float>, size: Float) -> Result<u32, JsValue> {
+        self.add(SdfNode::Cube { center, size })
+    }
+
+    pub fn capsule(
+        &mut self,
+        from: Vec<Float>,
+        to: Vec<Float>,
+        radius: Float,
+    ) -> Result<u32, JsValue> {
+        self.add(SdfNode::Capsule { from, to, radius })
+    }
+
+    /// Everything where `normal . p <= offset`
+    pub fn plane(&mut self, normal: Vec<Float>, offset: Float) -> Result<u32, JsValue> {
+        self.add(SdfNode::Plane { normal, offset })
+    }
+
+    /// A cube with holes cut through it recursively, wherever at least two
+    /// coordinates are in the middle third
+    pub fn menger(&mut self, center: Vec<Float>, size: Float, depth: u32) -> Result<u32, JsValue> {
+        self.add(SdfNode::Menger {
+            center,
+            size,
+            depth,
+        })
+    }
+
+    /// A regular simplex with a circumradius of `radius` that is recursively
+    /// replaced by copies of half the size at its vertices
+    pub fn sierpinski(
+        &mut self,
+        center: Vec<Float>,
+        radius: Float,
+        depth: u32,
+    ) -> Result<u32, JsValue> {
+        self.add(SdfNode::Sierpinski {
+            center,
+            radius,
+            depth,
+        })
+    }
+
+    pub fn union(&mut self, a: u32, b: u32) -> Result<u32, JsValue> {
+        self.add(SdfNode::Union(a, b))
+    }
+
+    pub fn intersection(&mut self, a: u32, b: u32) -> Result<u32, JsValue> {
+        self.add(SdfNode::Intersection(a, b))
+    }
+
+    /// `a` without `b`
+    pub fn difference(&mut self, a: u32, b: u32) -> Result<u32, JsValue> {
+        self.add(SdfNode::Difference(a, b))
+    }
+
+    /// Union that blends the shapes together within the distance `smoothness`
+    pub fn smooth_union(&mut self, a: u32, b: u32, smoothness: Float) -> Result<u32, JsValue> {
+        self.add(SdfNode::SmoothUnion(a, b, smoothness))
+    }
+
+    /// Grows the shape by `radius` which rounds off the edges
+    pub fn round(&mut self, a: u32, radius: Float) -> Result<u32, JsValue> {
+        self.add(SdfNode::Round(a, radius))
+    }
+
+    /// Rotates the xy plane proportional to the z coordinate, `rate` is in radians per unit
+    pub fn twist(&mut self, a: u32, rate: Float) -> Result<u32, JsValue> {
+        self.add(SdfNode::Twist(a, rate))
+    }
+}
+
+// Node of a constructive solid geometry tree, operators reference the nodes added before them
+#[derive(Debug, Clone)]
+pub(crate) enum CsgNode {
+    Sphere(Vec<Float>, Sphere),
+    Cube(Vec<Float>, Cube),
+    Union(u32, u32),
+    Intersection(u32, u32),
+    Difference(u32, u32),
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Csg {
+    // The last node is the root
+    pub(crate) nodes: Vec<CsgNode>,
+}
+
+#[wasm_bindgen]
+impl Csg {
+    /// A shape combined from other primitives. Every method adds a node and
+    /// returns its id to be used by the operators.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self { nodes: vec![] }
+    }
+
+    fn add(&mut self, node: CsgNode) -> u32 {
+        self.nodes.push(node);
+        (self.nodes.len() - 1) as u32
+    }
+
+    pub fn add_sphere(&mut self, pos: Vec<Float>, sphere: Sphere) -> u32 {
+        self.add(CsgNode::Sphere(pos, sphere))
+    }
+
+    pub fn add_cube(&mut self, pos: Vec<Float>, cube: Cube) -> u32 {
+        self.add(CsgNode::Cube(pos, cube))
+    }
+
+    // Operators can only refer to earlier nodes, so the tree has no cycles
+    fn add_operator(&mut self, node: CsgNode, a: u32, b: u32) -> Result<u32, JsValue> {
+        let id = self.nodes.len() as u32;
+        if a >= id || b >= id {
+            return Err(JsValue::from_str(&format!(
+                "node {} can only combine earlier nodes, not {} and {}",
+                id, a, b
+            )));
+        }
+
+        Ok(self.add(node))
+    }
+
+    pub fn union(&mut self, a: u32, b: u32) -> Result<u32, JsValue> {
+        self.add_operator(CsgNode::Union(a, b), a, b)
+    }
+
+    pub fn intersection(&mut self, a: u32, b: u32) -> Result<u32, JsValue> {
+        self.add_operator(CsgNode::Intersection(a, b), a, b)
+    }
+
+    /// `a` without `b`, the cut takes on the surface of `b`
+    pub fn difference(&mut self, a: u32, b: u32) -> Result<u32, JsValue> {
+        self.add_operator(CsgNode::Difference(a, b), a, b)
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Metaballs {
+    // Center, radius of influence and strength of every ball
+    pub(crate) balls: Vec<(Vec<Float>, Float, Float)>,
+    pub(crate) threshold: Float,
+    pub(crate) surface: Surface,
+}
+
+#[wasm_bindgen]
+impl Metaballs {
+    /// The surface where the summed up field of all balls reaches `threshold`
+    #[wasm_bindgen(constructor)]
+    pub fn new(threshold: Float, color: Color, reflection: Option<Float>) -> Self {
+        Self {
+            balls: vec![],
+            threshold,
+            surface: Surface::new(color, reflection),
+        }
+    }
+
+    /// A ball adds `strength` at its center, falling off to zero at `radius`.
+    /// Negative strengths carve into the other balls.
+    pub fn add_ball(&mut self, center: Vec<Float>, radius: Float, strength: Float) {
+        self.balls.push((center, radius, strength));
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct FunctionGraph {
+    pub(crate) expression: Expr,
+    pub(crate) extent: Float,
+    pub(crate) surface: Surface,
+}
+
+#[wasm_bindgen]
+impl FunctionGraph {
+    /// The graph of `expression` over the variables x1, x2, ... with z as the
+    /// height. The variables are the remaining axes in order, i.e. x, y, w, ...
+    /// and the graph is drawn where all of them are within -extent and extent.
+    /// Supports + - * / ^, parentheses, pi, e and the functions sin, cos, tan,
+    /// abs, sqrt, exp, ln, min and max.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        expression: &str,
+        extent: Float,
+        color: Color,
+        reflection: Option<Float>,
+    ) -> Result<FunctionGraph, JsValue> {
+        let expression = Expr::parse(expression).map_err(|message| JsValue::from_str(&message))?;
+
+        Ok(Self {
+            expression,
+            extent,
+            surface: Surface::new(color, reflection),
+        })
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Lattice {
+    pub(crate) basis: Vec<Vec<Float>>,
+    // Spheres placed relative to every lattice point
+    pub(crate) motif: Vec<(Vec<Float>, Sphere)>,
+    pub(crate) max_distance: Float,
+}
+
+#[wasm_bindgen]
+impl Lattice {
+    /// An infinitely repeating lattice, traced up to `max_distance` which has to
+    /// be finite. In N dimensions it needs exactly N basis vectors.
+    #[wasm_bindgen(constructor)]
+    pub fn new(max_distance: Float) -> Result<Lattice, JsValue> {
+        if !max_distance.is_finite() || max_distance < 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "the lattice needs a finite max distance, got {}",
+                max_distance
+            )));
+        }
+
+        Ok(Self {
+            basis: vec![],
+            motif: vec![],
+            max_distance,
+        })
+    }
+
+    pub fn add_basis_vector(&mut self, vector: Vec<Float>) {
+        self.basis.push(vector);
+    }
+
+    pub fn add_sphere(&mut self, offset: Vec<Float>, sphere: Sphere) {
+        self.motif.push((offset, sphere));
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum TransformStep {
+    Translate(Vec<Float>),
+    Scale(Float),
+    ScaleAxis(usize, Float),
+    // Rotation in the plane of two axes
+    Rotate(usize, usize, Float),
+    // Adds `factor` times the second axis to the first one
+    Shear(usize, usize, Float),
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Transform {
+    // Applied in order
+    pub(crate) steps: Vec<TransformStep>,
+}
+
+#[wasm_bindgen]
+impl Transform {
+    /// An affine transformation, starts out as the identity
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self { steps: vec![] }
+    }
+
+    pub fn translate(&mut self, offset: Vec<Float>) {
+        self.steps.push(TransformStep::Translate(offset));
+    }
+
+    pub fn scale(&mut self, factor: Float) {
+        self.steps.push(TransformStep::Scale(factor));
+    }
+
+    pub fn scale_axis(&mut self, axis: usize, factor: Float) {
+        self.steps.push(TransformStep::ScaleAxis(axis, factor));
+    }
+
+    /// Rotates from `axis_a` towards `axis_b` by `angle` (in radians)
+    pub fn rotate(&mut self, axis_a: usize, axis_b: usize, angle: Float) {
+        self.steps
+            .push(TransformStep::Rotate(axis_a, axis_b, angle));
+    }
+
+    pub fn shear(&mut self, axis: usize, by_axis: usize, factor: Float) {
+        self.steps.push(TransformStep::Shear(axis, by_axis, factor));
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Instance {
+    pub(crate) prototype: u32,
+    pub(crate) transform: Transform,
+    // Replaces the surfaces of all objects of the prototype
+    pub(crate) surface: Option<Surface>,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct RegularPolytope {
+    inner: ndrt_lib::RegularPolytope,
+}
+
+#[wasm_bindgen]
+impl RegularPolytope {
+    pub fn simplex(dimension: usize) -> Self {
+        Self {
+            inner: ndrt_lib::RegularPolytope::simplex(dimension),
+        }
+    }
+
+    pub fn hypercube(dimension: usize) -> Self {
+        Self {
+            inner: ndrt_lib::RegularPolytope::hypercube(dimension),
+        }
+    }
+
+    pub fn cross_polytope(dimension: usize) -> Self {
+        Self {
+            inner: ndrt_lib::RegularPolytope::cross_polytope(dimension),
+        }
+    }
+
+    pub fn cell_24() -> Self {
+        Self {
+            inner: ndrt_lib::RegularPolytope::cell_24(),
+        }
+    }
+
+    pub fn cell_120() -> Self {
+        Self {
+            inner: ndrt_lib::RegularPolytope::cell_120(),
+        }
+    }
+
+    pub fn cell_600() -> Self {
+        Self {
+            inner: ndrt_lib::RegularPolytope::cell_600(),
+        }
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.inner.dimension
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.inner.vertices.len()
+    }
+
+    /// The vertex scaled to a circumradius of `radius`
+    pub fn vertex(&self, index: usize, radius: Float) -> Vec<Float> {
+        self.inner.vertices[index]
+            .iter()
+            .map(|c| c * radius)
+            .collect()
+    }
+
+    /// Pairs of vertex indices
+    pub fn edges(&self) -> Vec<u32> {
+        self.inner
+            .edges
+            .iter()
+            .flat_map(|(a, b)| vec![*a as u32, *b as u32])
+            .collect()
+    }
+
+    /// The solid polytope with a circumradius of `radius`
+    pub fn to_polytope(&self, radius: Float, color: Color, reflection: Option<Float>) -> Polytope {
+        let mut polytope = Polytope::new(color, reflection);
+
+        for (normal, offset) in &self.inner.facets {
+            polytope.add_half_space(normal.clone(), offset * radius);
+        }
+
+        // The facets close the shape and all vertices are on the circumsphere
+        polytope.bounds_radius = Some(radius);
+
+        polytope
+    }
+}
+
+// Structure to find the objects along a ray
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Acceleration {
+    // Every ray is tested against every object
+    BruteForce,
+    // Bounding volume hierarchy
+    Bvh,
+    // Regular grid of cells over all objects, good for dense and evenly spread scenes
+    Grid,
 }
 
 #[wasm_bindgen]
@@ -71,7 +836,27 @@ impl Cube {
 pub struct World {
     pub(crate) spheres: Vec<(Vec<Float>, Sphere)>,
     pub(crate) cubes: Vec<(Vec<Float>, Cube)>,
+    pub(crate) cones: Vec<(Vec<Float>, Cone)>,
+    pub(crate) capsules: Vec<(Vec<Float>, Vec<Float>, Capsule)>,
+    pub(crate) simplices: Vec<(Vec<Float>, Simplex)>,
+    pub(crate) complexes: Vec<(Vec<Float>, SimplicialComplex)>,
+    pub(crate) polytopes: Vec<(Vec<Float>, Polytope)>,
+    pub(crate) tori: Vec<(Vec<Float>, Hypertorus)>,
+    pub(crate) sdfs: Vec<(Vec<Float>, Sdf)>,
+    pub(crate) csgs: Vec<(Vec<Float>, Csg)>,
+    pub(crate) metaballs: Vec<(Vec<Float>, Metaballs)>,
+    pub(crate) graphs: Vec<(Vec<Float>, FunctionGraph)>,
+    pub(crate) lattices: Vec<(Vec<Float>, Lattice)>,
+    pub(crate) prototypes: Vec<World>,
+    pub(crate) instances: Vec<Instance>,
+    // Shared by all simplicial complexes
+    pub(crate) vertices: Vec<Vec<Float>>,
     pub(crate) lights: Vec<(Vec<Float>, Light)>,
+    pub(crate) acceleration: Acceleration,
+    pub(crate) packet_tracing: bool,
+    // The world padded to each dimension it was rendered in, see
+    // `DimensionalWorld::cached`. Cleared whenever the world changes.
+    pub(crate) dimensional: RefCell<HashMap<usize, Rc<dyn Any>>>,
 }
 
 #[wasm_bindgen]
@@ -81,22 +866,188 @@ impl World {
         Self {
             spheres: vec![],
             cubes: vec![],
+            cones: vec![],
+            capsules: vec![],
+            simplices: vec![],
+            complexes: vec![],
+            polytopes: vec![],
+            tori: vec![],
+            sdfs: vec![],
+            csgs: vec![],
+            metaballs: vec![],
+            graphs: vec![],
+            lattices: vec![],
+            prototypes: vec![],
+            instances: vec![],
+            vertices: vec![],
             lights: vec![],
+            acceleration: Acceleration::Bvh,
+            packet_tracing: true,
+            dimensional: RefCell::new(HashMap::new()),
         }
     }
 
     #[wasm_bindgen]
     pub fn add_sphere(&mut self, pos: Vec<Float>, sphere: Sphere) {
+        self.changed();
         self.spheres.push((pos, sphere));
     }
 
     #[wasm_bindgen]
     pub fn add_cube(&mut self, pos: Vec<Float>, cube: Cube) {
+        self.changed();
         self.cubes.push((pos, cube));
     }
 
+    #[wasm_bindgen]
+    pub fn add_cone(&mut self, apex: Vec<Float>, cone: Cone) {
+        self.changed();
+        self.cones.push((apex, cone));
+    }
+
+    #[wasm_bindgen]
+    pub fn add_capsule(&mut self, from: Vec<Float>, to: Vec<Float>, capsule: Capsule) {
+        self.changed();
+        self.capsules.push((from, to, capsule));
+    }
+
+    #[wasm_bindgen]
+    pub fn add_simplex(&mut self, pos: Vec<Float>, simplex: Simplex) {
+        self.changed();
+        self.simplices.push((pos, simplex));
+    }
+
+    /// Adds a vertex that can be referenced by the facets of a `SimplicialComplex`
+    #[wasm_bindgen]
+    pub fn add_vertex(&mut self, pos: Vec<Float>) -> u32 {
+        self.changed();
+        self.vertices.push(pos);
+        (self.vertices.len() - 1) as u32
+    }
+
+    /// Fails if a facet refers to a vertex that has not been added yet
+    #[wasm_bindgen]
+    pub fn add_complex(
+        &mut self,
+        pos: Vec<Float>,
+        complex: SimplicialComplex,
+    ) -> Result<(), JsValue> {
+        let vertex_count = self.vertices.len();
+        if let Some(index) = complex.facets.iter().find(|i| **i as usize >= vertex_count) {
+            return Err(JsValue::from_str(&format!(
+                "there is no vertex {}, only {} were added",
+                index, vertex_count
+            )));
+        }
+
+        self.changed();
+        self.complexes.push((pos, complex));
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn add_polytope(&mut self, pos: Vec<Float>, polytope: Polytope) {
+        self.changed();
+        self.polytopes.push((pos, polytope));
+    }
+
+    #[wasm_bindgen]
+    pub fn add_hypertorus(&mut self, pos: Vec<Float>, torus: Hypertorus) {
+        self.changed();
+        self.tori.push((pos, torus));
+    }
+
+    #[wasm_bindgen]
+    pub fn add_sdf(&mut self, pos: Vec<Float>, sdf: Sdf) {
+        self.changed();
+        self.sdfs.push((pos, sdf));
+    }
+
+    #[wasm_bindgen]
+    pub fn add_csg(&mut self, pos: Vec<Float>, csg: Csg) {
+        self.changed();
+        self.csgs.push((pos, csg));
+    }
+
+    #[wasm_bindgen]
+    pub fn add_metaballs(&mut self, pos: Vec<Float>, metaballs: Metaballs) {
+        self.changed();
+        self.metaballs.push((pos, metaballs));
+    }
+
+    #[wasm_bindgen]
+    pub fn add_graph(&mut self, origin: Vec<Float>, graph: FunctionGraph) {
+        self.changed();
+        self.graphs.push((origin, graph));
+    }
+
+    #[wasm_bindgen]
+    pub fn add_lattice(&mut self, origin: Vec<Float>, lattice: Lattice) {
+        self.changed();
+        self.lattices.push((origin, lattice));
+    }
+
+    /// Adds a group of objects that can be placed multiple times using
+    /// `add_instance`, lights of the prototype are ignored
+    #[wasm_bindgen]
+    pub fn add_prototype(&mut self, prototype: World) -> u32 {
+        self.changed();
+        self.prototypes.push(prototype);
+        (self.prototypes.len() - 1) as u32
+    }
+
+    #[wasm_bindgen]
+    pub fn add_instance(&mut self, prototype: u32, transform: &Transform) {
+        self.changed();
+        self.instances.push(Instance {
+            prototype,
+            transform: transform.clone(),
+            surface: None,
+        });
+    }
+
+    #[wasm_bindgen]
+    pub fn add_instance_with_surface(
+        &mut self,
+        prototype: u32,
+        transform: &Transform,
+        color: Color,
+        reflection: Option<Float>,
+    ) {
+        self.changed();
+        self.instances.push(Instance {
+            prototype,
+            transform: transform.clone(),
+            surface: Some(Surface::new(color, reflection)),
+        });
+    }
+
     #[wasm_bindgen]
     pub fn add_light(&mut self, pos: Vec<Float>, light: Light) {
+        self.changed();
         self.lights.push((pos, light));
     }
+
+    /// How rays find the objects they might hit, a bounding volume
+    /// hierarchy unless set otherwise
+    #[wasm_bindgen]
+    pub fn set_acceleration(&mut self, acceleration: Acceleration) {
+        self.changed();
+        self.acceleration = acceleration;
+    }
+
+    /// Whether the first samples of a frame are traced in packets of
+    /// neighbouring rays, on unless set otherwise
+    #[wasm_bindgen]
+    pub fn set_packet_tracing(&mut self, enabled: bool) {
+        // Only affects rendering, the cached worlds stay valid
+        self.packet_tracing = enabled;
+    }
+}
+
+impl World {
+    // Drops the padded copies, they are out of date
+    fn changed(&mut self) {
+        self.dimensional.get_mut().clear();
+    }
 }